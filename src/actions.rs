@@ -1,10 +1,13 @@
 use bevy::prelude::*;
+use bindings::Bindings;
 use leafwing_input_manager::prelude::*;
 
+pub mod bindings;
+
 pub fn plugin(app: &mut App) {
     app.add_plugins(InputManagerPlugin::<Action>::default())
-        .init_resource::<ActionState<Action>>()
-        .insert_resource(input_map());
+        .init_resource::<ActionState<Action>>();
+    bindings::plugin(app);
 }
 
 #[derive(Actionlike, PartialEq, Eq, Hash, Clone, Copy, Debug, Reflect)]
@@ -14,16 +17,76 @@ pub enum Action {
     Left,
     Right,
     Pull,
+    /// Toggle pulling everything connected to what is pulled.
+    Sticky,
+    /// Toggle shoving bodies along when moving into them.
+    Push,
+    /// Rotate the bodies connected to the selector clockwise.
+    Rotate,
+    RotateHalf,
+    Mirror,
+    /// Toggle whether the selector's cell is selected.
+    Select,
+    /// Hold to select every cell in the box between where the selector was when pressed and when released.
+    BoxSelect,
+    Deselect,
+    Restart,
+    Exit,
+}
+
+impl Action {
+    /// Every action, in the order they are rebound.
+    pub const ALL: [Action; 15] = [
+        Action::Up,
+        Action::Down,
+        Action::Left,
+        Action::Right,
+        Action::Pull,
+        Action::Sticky,
+        Action::Push,
+        Action::Rotate,
+        Action::RotateHalf,
+        Action::Mirror,
+        Action::Select,
+        Action::BoxSelect,
+        Action::Deselect,
+        Action::Restart,
+        Action::Exit,
+    ];
 }
 
-fn input_map() -> InputMap<Action> {
-    InputMap::new([
-        (Action::Up, KeyCode::KeyW),
-        (Action::Down, KeyCode::KeyS),
-        (Action::Left, KeyCode::KeyA),
-        (Action::Right, KeyCode::KeyD),
-        (Action::Pull, KeyCode::KeyF),
-    ])
+/// The player's keyboard bindings, and the default gamepad bindings.
+pub fn input_map(bindings: &Bindings) -> InputMap<Action> {
+    /// How far the stick has to be pushed before it counts as a press.
+    const STICK_DEADZONE: f32 = 0.5;
+
+    let mut input_map = InputMap::default();
+    bindings.keys().for_each(|(action, keys)| {
+        input_map.insert_multiple(*action, keys.iter().copied());
+    });
+    input_map
+        .with(Action::Up, GamepadButton::DPadUp)
+        .with(Action::Down, GamepadButton::DPadDown)
+        .with(Action::Left, GamepadButton::DPadLeft)
+        .with(Action::Right, GamepadButton::DPadRight)
+        // The stick acts like buttons, so it moves a cell at a time, with the same repeat timing as the keys.
+        .with(
+            Action::Up,
+            GamepadControlDirection::LEFT_UP.threshold(STICK_DEADZONE),
+        )
+        .with(
+            Action::Down,
+            GamepadControlDirection::LEFT_DOWN.threshold(STICK_DEADZONE),
+        )
+        .with(
+            Action::Left,
+            GamepadControlDirection::LEFT_LEFT.threshold(STICK_DEADZONE),
+        )
+        .with(
+            Action::Right,
+            GamepadControlDirection::LEFT_RIGHT.threshold(STICK_DEADZONE),
+        )
+        .with(Action::Pull, GamepadButton::South)
 }
 
 pub type Actions<'w> = Res<'w, ActionState<Action>>;
@@ -0,0 +1,305 @@
+use super::{Action, input_map};
+use crate::{config, replay::Replay};
+use bevy::{
+    prelude::*,
+    reflect::{DynamicEnum, DynamicVariant, Enum},
+};
+use leafwing_input_manager::prelude::*;
+use std::collections::HashMap;
+
+pub fn plugin(app: &mut App) {
+    let bindings = Bindings::load();
+    app.insert_resource(input_map(&bindings))
+        .insert_resource(bindings)
+        .add_systems(Startup, spawn_prompt)
+        .add_systems(
+            Update,
+            (
+                // Real keys are ignored while replaying, so they can't change the bindings either.
+                (start_rebinding, reset)
+                    .run_if(not(resource_exists::<Rebinding>).and(not(resource_exists::<Replay>))),
+                rebind.run_if(resource_exists::<Rebinding>),
+                apply.run_if(config::changed::<Bindings>),
+                prompt,
+            )
+                .chain(),
+        );
+}
+
+/// The file in the config directory the bindings are kept in.
+const FILE_NAME: &str = "bindings.txt";
+
+/// Starts rebinding every action, one at a time. While rebinding, cancels it, keeping the bindings as they were.
+const REBIND_KEY: KeyCode = KeyCode::F2;
+/// Puts every binding back to the default.
+const RESET_KEY: KeyCode = KeyCode::F3;
+/// While rebinding, keeps the action's current keys and moves on to the next action.
+const SKIP_KEY: KeyCode = KeyCode::Escape;
+/// While a key conflicts, takes it from the other action, leaving it without that key.
+/// Pressing the conflicting key again swaps instead.
+const UNBIND_KEY: KeyCode = KeyCode::Delete;
+
+/// The keys bound to each action.
+/// Saved to the config directory whenever it changes, and loaded on startup.
+#[derive(Resource, Clone, Debug)]
+pub struct Bindings(HashMap<Action, Vec<KeyCode>>);
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Self(HashMap::from_iter(
+            [
+                (Action::Up, KeyCode::KeyW),
+                (Action::Down, KeyCode::KeyS),
+                (Action::Left, KeyCode::KeyA),
+                (Action::Right, KeyCode::KeyD),
+                (Action::Pull, KeyCode::KeyF),
+                (Action::Sticky, KeyCode::KeyG),
+                (Action::Push, KeyCode::KeyV),
+                (Action::Rotate, KeyCode::KeyE),
+                (Action::RotateHalf, KeyCode::KeyQ),
+                (Action::Mirror, KeyCode::KeyX),
+                (Action::Select, KeyCode::Space),
+                (Action::BoxSelect, KeyCode::ShiftLeft),
+                (Action::Deselect, KeyCode::KeyC),
+                (Action::Restart, KeyCode::KeyR),
+                (Action::Exit, KeyCode::Escape),
+            ]
+            .map(|(action, key)| (action, vec![key])),
+        ))
+    }
+}
+
+impl Bindings {
+    pub fn keys(&self) -> impl Iterator<Item = (&Action, &Vec<KeyCode>)> {
+        self.0.iter()
+    }
+
+    /// Which other action the key is already bound to.
+    pub fn conflict(&self, action: Action, key: KeyCode) -> Option<Action> {
+        self.0
+            .iter()
+            .find(|(other, keys)| **other != action && keys.contains(&key))
+            .map(|(other, _)| *other)
+    }
+
+    /// Loads the saved bindings. Anything that isn't saved, or can't be read, uses the default.
+    /// If nothing has been saved yet, the defaults are saved, so there is a file to edit.
+    fn load() -> Self {
+        let mut bindings = Self::default();
+        // Each line is `Action = Key, Key`.
+        let missing = config::load(FILE_NAME, |action, keys| {
+            let Some(action) = from_variant_name::<Action>(action) else {
+                return Err(format!("Unknown action {action:?}."));
+            };
+            let keys = keys
+                .split(',')
+                .map(str::trim)
+                .filter(|key| !key.is_empty())
+                .map(|key| from_variant_name::<KeyCode>(key).ok_or(key))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|key| format!("Unknown key {key:?}."))?;
+            bindings.0.insert(action, keys);
+            Ok(())
+        });
+        if missing {
+            bindings.save();
+        }
+        bindings
+    }
+
+    fn save(&self) {
+        let lines = Action::ALL.into_iter().map(|action| {
+            let keys = self
+                .0
+                .get(&action)
+                .map(|keys| {
+                    keys.iter()
+                        .map(|key| key.variant_name())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                })
+                .unwrap_or_default();
+            (action.variant_name().to_owned(), keys)
+        });
+        config::save(FILE_NAME, lines);
+    }
+}
+
+/// Gets a unit variant of an enum from its name.
+fn from_variant_name<T: FromReflect>(name: &str) -> Option<T> {
+    T::from_reflect(&DynamicEnum::new(name.to_owned(), DynamicVariant::Unit))
+}
+
+/// Rebinding is in progress.
+/// Goes through every action in order, with the next key pressed being bound to it.
+#[derive(Resource)]
+struct Rebinding {
+    /// Index into Action::ALL.
+    index: usize,
+    /// Only applied once every action is done.
+    bindings: Bindings,
+    /// The last key pressed was already bound to another action.
+    conflict: Option<(KeyCode, Action)>,
+    /// The last key pressed can't be bound, as it resets the bindings.
+    reserved: Option<KeyCode>,
+}
+
+fn start_rebinding(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<Bindings>,
+) {
+    if !keys.just_pressed(REBIND_KEY) {
+        return;
+    }
+    commands.insert_resource(Rebinding {
+        index: 0,
+        bindings: bindings.clone(),
+        conflict: None,
+        reserved: None,
+    });
+    // Stop the keys being rebound from also doing their actions.
+    commands.insert_resource(InputMap::<Action>::default());
+}
+
+fn reset(keys: Res<ButtonInput<KeyCode>>, mut bindings: ResMut<Bindings>) {
+    if keys.just_pressed(RESET_KEY) {
+        info!("Reset bindings to the defaults.");
+        *bindings = Bindings::default();
+    }
+}
+
+fn rebind(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut rebinding: ResMut<Rebinding>,
+    mut bindings: ResMut<Bindings>,
+) {
+    // The key that started rebinding is still just pressed.
+    if rebinding.is_added() {
+        return;
+    }
+    let Some(key) = keys.get_just_pressed().next().copied() else {
+        return;
+    };
+    let action = Action::ALL[rebinding.index];
+
+    if key == REBIND_KEY {
+        info!("Cancelled rebinding.");
+        commands.remove_resource::<Rebinding>();
+        commands.insert_resource(input_map(&bindings));
+        return;
+    }
+    if key == RESET_KEY {
+        rebinding.reserved = Some(key);
+        return;
+    }
+    rebinding.reserved = None;
+
+    if key != SKIP_KEY {
+        let resolving = rebinding
+            .conflict
+            .filter(|(conflicting, _)| key == *conflicting || key == UNBIND_KEY);
+        if let Some((conflicting, other)) = resolving {
+            let current = rebinding
+                .bindings
+                .0
+                .get(&action)
+                .cloned()
+                .unwrap_or_default();
+            let other_keys = rebinding.bindings.0.entry(other).or_default();
+            other_keys.retain(|other_key| *other_key != conflicting);
+            if key == conflicting {
+                // Swap, so the other action gets this action's keys.
+                current.into_iter().for_each(|current_key| {
+                    if !other_keys.contains(&current_key) {
+                        other_keys.push(current_key);
+                    }
+                });
+            }
+            rebinding.bindings.0.insert(action, vec![conflicting]);
+        } else if let Some(other) = rebinding.bindings.conflict(action, key) {
+            rebinding.conflict = Some((key, other));
+            return;
+        } else {
+            rebinding.bindings.0.insert(action, vec![key]);
+        }
+    }
+    rebinding.conflict = None;
+    rebinding.index += 1;
+
+    if rebinding.index >= Action::ALL.len() {
+        *bindings = rebinding.bindings.clone();
+        commands.remove_resource::<Rebinding>();
+    }
+}
+
+/// Rebuilds the input map from the bindings, and saves them.
+fn apply(mut commands: Commands, bindings: Res<Bindings>) {
+    commands.insert_resource(input_map(&bindings));
+    bindings.save();
+}
+
+/// Tells the player what to press while rebinding.
+#[derive(Component)]
+struct RebindingPrompt;
+
+fn spawn_prompt(mut commands: Commands) {
+    commands.spawn((
+        RebindingPrompt,
+        Text::default(),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(12.),
+            left: Val::Px(12.),
+            ..default()
+        },
+    ));
+}
+
+fn prompt(rebinding: Option<Res<Rebinding>>, mut text: Single<&mut Text, With<RebindingPrompt>>) {
+    let Some(rebinding) = rebinding else {
+        if !text.0.is_empty() {
+            text.0.clear();
+        }
+        return;
+    };
+    let Some(action) = Action::ALL.get(rebinding.index) else {
+        return;
+    };
+
+    let current = rebinding
+        .bindings
+        .0
+        .get(action)
+        .map(|keys| {
+            keys.iter()
+                .map(|key| key.variant_name())
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+        .unwrap_or_default();
+    let mut prompt = format!(
+        "Press a key for {} ({}/{}). {} keeps {current}, and {} cancels.",
+        action.variant_name(),
+        rebinding.index + 1,
+        Action::ALL.len(),
+        SKIP_KEY.variant_name(),
+        REBIND_KEY.variant_name(),
+    );
+    if let Some((key, other)) = rebinding.conflict {
+        prompt.push_str(&format!(
+            "\n{key} is already bound to {other}. Press {key} again to swap, or {} to unbind it from {other}.",
+            UNBIND_KEY.variant_name(),
+            key = key.variant_name(),
+            other = other.variant_name(),
+        ));
+    }
+    if let Some(key) = rebinding.reserved {
+        prompt.push_str(&format!(
+            "\n{} can't be bound, as it resets the bindings.",
+            key.variant_name()
+        ));
+    }
+    text.0 = prompt;
+}
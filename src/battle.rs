@@ -1,27 +1,37 @@
-use bevy::{math::U8Vec2, prelude::*};
-use body::Body;
-use grid::Grid;
-use opponent::{OpponentGrid, OpponentGridSpeed};
-use selector::Selector;
-
-use crate::create_grid;
+use bevy::prelude::*;
+use state::BattleState;
 
 mod body;
+mod definition;
+mod fit;
 mod grid;
+mod health;
 mod opponent;
-mod selector;
+mod piece;
+pub mod selector;
+mod state;
+mod wall;
+mod wave;
 
 pub fn plugin(app: &mut App) {
     grid::plugin(app);
     selector::plugin(app);
     opponent::plugin(app);
     body::plugin(app);
+    fit::plugin(app);
+    wall::plugin(app);
+    health::plugin(app);
+    state::plugin(app);
+    definition::plugin(app);
+    wave::plugin(app);
     app.add_systems(PreStartup, cube_mesh)
-        .add_systems(Startup, experiment);
+        .add_systems(Startup, camera)
+        .add_systems(OnEnter(BattleState::Intro), clean_up)
+        .add_systems(OnEnter(BattleState::Exited), clean_up);
 }
 
-/// Temp battle experiment starter.
-fn experiment(mut commands: Commands) {
+/// The camera isn't from a battle, so it stays between them.
+fn camera(mut commands: Commands) {
     commands.spawn((
         Transform {
             translation: Vec3::new(-10., 0., -10.),
@@ -35,27 +45,6 @@ fn experiment(mut commands: Commands) {
         //     ..OrthographicProjection::default_3d()
         // }),
     ));
-
-    // player
-    let s = Selector::on_grid();
-    let b = Body::on_grid();
-    create_grid!(
-        commands
-        [s| | | | ]
-        [ |b|b| | ]
-        [ |b|b|b| ]
-        [ | |b|b| ]
-        [ | | | | ]
-    );
-
-    // opponent
-    commands.spawn((
-        Grid::new(U8Vec2::splat(5)),
-        Transform::from_xyz(0., 0., 20.),
-        OpponentGrid::default(),
-    ));
-
-    commands.spawn(OpponentGridSpeed(2.5));
 }
 
 /// All entities related to the battle will have this.
@@ -63,6 +52,21 @@ fn experiment(mut commands: Commands) {
 #[derive(Component, Default)]
 struct FromBattle;
 
+/// Despawns everything from the previous battle.
+fn clean_up(
+    mut commands: Commands,
+    from_battle: Query<Entity, (With<FromBattle>, Without<Parent>)>,
+) {
+    from_battle.iter().for_each(|entity| {
+        commands.entity(entity).despawn_recursive();
+    });
+}
+
+/// The grid the player's bodies are on.
+/// Singleton.
+#[derive(Component)]
+pub struct PlayerGrid;
+
 #[derive(Resource)]
 struct CubeMesh(Handle<Mesh>);
 
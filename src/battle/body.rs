@@ -1,16 +1,17 @@
 use super::{
-    CubeMesh,
+    CubeMesh, FromBattle,
+    piece::PieceOf,
     selector::{OnlyOneInCell, Pullable},
 };
 use bevy::prelude::*;
 
 pub fn plugin(app: &mut App) {
-    app.add_systems(PreStartup, material);
+    app.add_systems(PreStartup, (material, piece_material));
 }
 
 /// A player's body block.
 #[derive(Component, Default)]
-#[require(Pullable)]
+#[require(Pullable, FromBattle)]
 pub struct Body;
 
 impl Body {
@@ -29,6 +30,23 @@ impl Body {
             entity.id()
         }
     }
+
+    /// Like on_grid, but every body spawned is part of the piece.
+    pub fn in_piece(piece: Entity) -> impl Fn(&mut Commands) -> Entity {
+        move |commands| {
+            let mut entity = commands.spawn((Body, PieceOf(piece)));
+            entity.queue(|mut entity: EntityWorldMut| {
+                let cube_mesh = entity.world().resource::<CubeMesh>().0.clone();
+                let piece_material = entity.world().resource::<PieceMaterial>().0.clone();
+                entity.insert((
+                    Mesh3d(cube_mesh),
+                    MeshMaterial3d(piece_material),
+                    OnlyOneInCell(std::any::TypeId::of::<Body>()),
+                ));
+            });
+            entity.id()
+        }
+    }
 }
 
 #[derive(Resource)]
@@ -46,6 +64,22 @@ fn material(world: &mut World) {
     world.insert_resource(material);
 }
 
+/// Pieces look different, so the player can tell which bodies move together.
+#[derive(Resource)]
+struct PieceMaterial(Handle<StandardMaterial>);
+
+fn piece_material(world: &mut World) {
+    let asset_server = world.resource::<AssetServer>();
+    let material = PieceMaterial(asset_server.add(StandardMaterial {
+        base_color_texture: None,
+        base_color: Color::srgb(0.1, 0.15, 0.4),
+        unlit: true,
+        alpha_mode: AlphaMode::Opaque,
+        ..default()
+    }));
+    world.insert_resource(material);
+}
+
 // It should be the other way round. Bodies should never need to collide on their own.
 // #[register]
 // fn collide(mut collision_event: EventReader<CollisionEvent>, grids: Query<&Grid>) {
@@ -0,0 +1,541 @@
+use super::{
+    PlayerGrid,
+    body::Body,
+    grid::layout::{Layout, LayoutError, Spawners},
+    health::Health,
+    opponent::{OpponentGridSpeed, speed::SpeedProfile},
+    piece::Piece,
+    selector::{Selector, constraints::SelectorConstraints},
+    state::{BattleState, BattleTransitions},
+    wall::Wall,
+    wave::WaveSequencer,
+};
+use crate::actions::Action;
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadState, io::Reader},
+    prelude::*,
+};
+use std::{error::Error, fmt::Display, num::NonZeroU8, str::FromStr, string::FromUtf8Error};
+
+pub fn plugin(app: &mut App) {
+    app.init_asset::<BattleDefinition>()
+        .init_asset_loader::<BattleDefinitionLoader>()
+        .add_systems(Startup, load)
+        .add_systems(
+            Update,
+            (
+                spawn.run_if(in_state(BattleState::Intro)),
+                // After the other transitions, so they can't leave the battle despawned outside of the intro.
+                (super::clean_up, reload)
+                    .chain()
+                    .after(BattleTransitions)
+                    .run_if(modified.and(not(in_state(BattleState::Exited)))),
+            ),
+        );
+}
+
+/// Everything needed to spawn a battle.
+/// Loaded from `.battle` files, which are made of sections. Each section is a header line, usually followed by the rows of a grid.
+/// Any line starting with `[` or containing `|` is a row, so brackets are only needed for rows with a single cell.
+/// The player's layout has exactly one selector `s`, and can have bodies `b` and pieces. Walls can only have walls `w`.
+/// Walls before the first wave header are in their own wave.
+/// ```text
+/// # Comment.
+/// player health=3
+/// [s| |b]
+/// [ |L|b]
+/// [ |L|L]
+///
+/// # Optional.
+/// selector pull=true freeze=2 region=0,0,2,1 disable=up,down
+///
+/// wave
+/// wall speed=2.5 delay=1
+/// [w| |w]
+/// [w| | ]
+///
+/// wall speed=accelerate:1,0.5,4 spacing=8
+/// [ | |w]
+/// [w|w|w]
+/// ```
+#[derive(Asset, TypePath, Debug)]
+pub struct BattleDefinition {
+    pub player: Layout,
+    pub health: u8,
+    /// Put on the player's selectors.
+    pub selector: SelectorConstraints,
+    /// In the order they start.
+    pub waves: Vec<WaveDefinition>,
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct WaveDefinition {
+    /// In the order they approach.
+    pub walls: Vec<WallDefinition>,
+}
+
+#[derive(Clone, Debug)]
+pub struct WallDefinition {
+    pub layout: Layout,
+    /// How fast the wall approaches the player.
+    pub speed: SpeedProfile,
+    /// When the wall spawns.
+    pub trigger: Trigger,
+}
+
+/// When a wall spawns, relative to the wall before it.
+#[derive(Clone, Copy, Debug)]
+pub enum Trigger {
+    /// Seconds after the previous wall spawned, or after dodging started for the first wall.
+    Delay(f32),
+    /// Once the previous wall has moved this far. The first wall spawns straight away.
+    Spacing(f32),
+}
+
+impl BattleDefinition {
+    pub fn parse(text: &str) -> Result<Self, BattleDefinitionError> {
+        // Split into the header line number, the header, and the rows.
+        let mut sections: Vec<(usize, &str, String)> = vec![];
+        for (line, text) in text.lines().enumerate() {
+            let line = line + 1;
+            let text = text.trim();
+            if text.is_empty() || text.starts_with('#') {
+                continue;
+            }
+
+            if text.starts_with('[') || text.contains('|') {
+                let Some((_, _, rows)) = sections.last_mut() else {
+                    return Err(BattleDefinitionError::RowOutsideSection { line });
+                };
+                rows.push_str(text);
+                rows.push('\n');
+            } else {
+                sections.push((line, text, String::new()));
+            }
+        }
+
+        let player_spawners = player_spawners();
+        let wall_spawners = wall_spawners();
+        let mut player = None;
+        let mut health = 3;
+        let mut selector: Option<SelectorConstraints> = None;
+        let mut waves: Vec<WaveDefinition> = vec![];
+
+        for (line, header, rows) in sections {
+            let mut words = header.split_whitespace();
+            let name = words.next().unwrap_or_default();
+            let mut properties = words.map(|word| {
+                word.split_once('=')
+                    .ok_or_else(|| BattleDefinitionError::InvalidProperty {
+                        line,
+                        property: word.to_owned(),
+                    })
+            });
+
+            // Each section has its own symbols, and only the player can have pieces.
+            let layout =
+                |spawners: &Spawners, pieces: bool| -> Result<Layout, BattleDefinitionError> {
+                    let layout = Layout::parse(&rows)
+                        .map_err(|error| BattleDefinitionError::Layout { line, error })?;
+                    if let Some(symbol) = layout.cells().iter().flatten().find(|symbol| {
+                        !spawners.contains_key(*symbol) && !(pieces && is_piece(symbol))
+                    }) {
+                        return Err(BattleDefinitionError::Layout {
+                            line,
+                            error: LayoutError::UnknownSymbol(symbol.clone()),
+                        });
+                    }
+                    Ok(layout)
+                };
+
+            match name {
+                "player" => {
+                    if player.is_some() {
+                        return Err(BattleDefinitionError::DuplicateSection {
+                            line,
+                            section: name.to_owned(),
+                        });
+                    }
+                    for property in properties {
+                        match property? {
+                            // At 0, the battle could never be lost.
+                            ("health", value) => {
+                                health = parse_property::<NonZeroU8>(line, "health", value)?.get()
+                            }
+                            (key, _) => return Err(unknown_property(line, key)),
+                        }
+                    }
+                    let layout = layout(&player_spawners, true)?;
+                    let selectors = layout
+                        .cells()
+                        .iter()
+                        .flatten()
+                        .filter(|symbol| *symbol == "s")
+                        .count();
+                    if selectors != 1 {
+                        return Err(BattleDefinitionError::SelectorCount {
+                            line,
+                            count: selectors,
+                        });
+                    }
+                    player = Some(layout);
+                }
+                "selector" => {
+                    if selector.is_some() {
+                        return Err(BattleDefinitionError::DuplicateSection {
+                            line,
+                            section: name.to_owned(),
+                        });
+                    }
+                    if !rows.is_empty() {
+                        return Err(BattleDefinitionError::UnexpectedRows { line });
+                    }
+                    let mut constraints = SelectorConstraints::default();
+                    for property in properties {
+                        match property? {
+                            ("pull", value) => {
+                                constraints.pull = Some(parse_property(line, "pull", value)?)
+                            }
+                            ("freeze", value) => {
+                                constraints.frozen_seconds = parse_property(line, "freeze", value)?
+                            }
+                            ("region", value) => {
+                                constraints.region = Some(parse_property(line, "region", value)?)
+                            }
+                            ("disable", value) => {
+                                constraints.disabled_directions = value
+                                    .split(',')
+                                    .map(|direction| match direction {
+                                        "up" => Ok(Action::Up),
+                                        "down" => Ok(Action::Down),
+                                        "left" => Ok(Action::Left),
+                                        "right" => Ok(Action::Right),
+                                        _ => Err(BattleDefinitionError::InvalidProperty {
+                                            line,
+                                            property: format!("disable={value}"),
+                                        }),
+                                    })
+                                    .collect::<Result<_, _>>()?
+                            }
+                            (key, _) => return Err(unknown_property(line, key)),
+                        }
+                    }
+                    selector = Some(constraints);
+                }
+                "wave" => {
+                    if !rows.is_empty() {
+                        return Err(BattleDefinitionError::UnexpectedRows { line });
+                    }
+                    if let Some(property) = properties.next() {
+                        return Err(unknown_property(line, property?.0));
+                    }
+                    waves.push(WaveDefinition::default());
+                }
+                "wall" => {
+                    let mut wall = WallDefinition {
+                        layout: layout(&wall_spawners, false)?,
+                        speed: SpeedProfile::Constant(1.),
+                        trigger: Trigger::Spacing(10.),
+                    };
+                    for property in properties {
+                        match property? {
+                            ("speed", value) => wall.speed = parse_property(line, "speed", value)?,
+                            ("delay", value) => {
+                                wall.trigger = Trigger::Delay(parse_property(line, "delay", value)?)
+                            }
+                            ("spacing", value) => {
+                                wall.trigger =
+                                    Trigger::Spacing(parse_property(line, "spacing", value)?)
+                            }
+                            (key, _) => return Err(unknown_property(line, key)),
+                        }
+                    }
+
+                    if waves.is_empty() {
+                        waves.push(WaveDefinition::default());
+                    }
+                    waves.last_mut().unwrap().walls.push(wall);
+                }
+                _ => {
+                    return Err(BattleDefinitionError::UnknownSection {
+                        line,
+                        section: name.to_owned(),
+                    });
+                }
+            }
+        }
+
+        // Without any walls, the battle would never end.
+        if waves.iter().all(|wave| wave.walls.is_empty()) {
+            return Err(BattleDefinitionError::MissingWalls);
+        }
+
+        Ok(Self {
+            player: player.ok_or(BattleDefinitionError::MissingPlayer)?,
+            health,
+            selector: selector.unwrap_or_default(),
+            waves,
+        })
+    }
+}
+
+fn parse_property<T: FromStr>(
+    line: usize,
+    key: &str,
+    value: &str,
+) -> Result<T, BattleDefinitionError> {
+    value
+        .parse()
+        .map_err(|_| BattleDefinitionError::InvalidProperty {
+            line,
+            property: format!("{key}={value}"),
+        })
+}
+
+fn unknown_property(line: usize, key: &str) -> BattleDefinitionError {
+    BattleDefinitionError::UnknownProperty {
+        line,
+        key: key.to_owned(),
+    }
+}
+
+/// What each symbol in the player's layout spawns, other than pieces.
+fn player_spawners() -> Spawners {
+    let mut spawners = Spawners::new();
+    spawners.insert("s".to_owned(), Box::new(Selector::on_grid()));
+    spawners.insert("b".to_owned(), Box::new(Body::on_grid()));
+    spawners
+}
+
+/// What each symbol in a wall's layout spawns.
+pub fn wall_spawners() -> Spawners {
+    let mut spawners = Spawners::new();
+    spawners.insert("w".to_owned(), Box::new(Wall::on_grid()));
+    spawners
+}
+
+/// Uppercase letters are pieces. Every cell in a layout with the same letter is part of the same piece.
+fn is_piece(symbol: &str) -> bool {
+    symbol.len() == 1
+        && symbol
+            .chars()
+            .all(|character| character.is_ascii_uppercase())
+}
+
+/// What each symbol in the player's layout spawns, spawning a new piece for each letter.
+pub fn layout_spawners(commands: &mut Commands, layout: &Layout) -> Spawners {
+    let mut spawners = player_spawners();
+    layout
+        .cells()
+        .iter()
+        .flatten()
+        .filter(|symbol| is_piece(symbol))
+        .for_each(|symbol| {
+            if !spawners.contains_key(symbol) {
+                let piece = commands.spawn(Piece::default()).id();
+                spawners.insert(symbol.clone(), Box::new(Body::in_piece(piece)));
+            }
+        });
+    spawners
+}
+
+#[derive(Debug)]
+pub enum BattleDefinitionError {
+    Io(std::io::Error),
+    Utf8(FromUtf8Error),
+    /// A row came before any section header.
+    RowOutsideSection {
+        line: usize,
+    },
+    /// A section that can't have rows had rows.
+    UnexpectedRows {
+        line: usize,
+    },
+    UnknownSection {
+        line: usize,
+        section: String,
+    },
+    /// A section that can only be written once was written again.
+    DuplicateSection {
+        line: usize,
+        section: String,
+    },
+    UnknownProperty {
+        line: usize,
+        key: String,
+    },
+    /// A property wasn't `key=value`, or the value couldn't be parsed.
+    InvalidProperty {
+        line: usize,
+        property: String,
+    },
+    Layout {
+        line: usize,
+        error: LayoutError,
+    },
+    /// The player's layout didn't have exactly one selector.
+    SelectorCount {
+        line: usize,
+        count: usize,
+    },
+    MissingPlayer,
+    MissingWalls,
+}
+
+impl Display for BattleDefinitionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "Could not read the battle: {error}"),
+            Self::Utf8(error) => write!(f, "The battle is not valid UTF-8: {error}"),
+            Self::RowOutsideSection { line } => {
+                write!(f, "Line {line}: The row is not in a section.")
+            }
+            Self::UnexpectedRows { line } => {
+                write!(f, "Line {line}: This section can't have rows.")
+            }
+            Self::UnknownSection { line, section } => {
+                write!(f, "Line {line}: Unknown section \"{section}\".")
+            }
+            Self::DuplicateSection { line, section } => {
+                write!(
+                    f,
+                    "Line {line}: There can only be one \"{section}\" section."
+                )
+            }
+            Self::UnknownProperty { line, key } => {
+                write!(f, "Line {line}: Unknown property \"{key}\".")
+            }
+            Self::InvalidProperty { line, property } => {
+                write!(f, "Line {line}: Invalid property \"{property}\".")
+            }
+            Self::Layout { line, error } => write!(f, "Line {line}: {error}"),
+            Self::SelectorCount { line, count } => write!(
+                f,
+                "Line {line}: The player needs exactly one selector, but has {count}."
+            ),
+            Self::MissingPlayer => write!(f, "There is no player section."),
+            Self::MissingWalls => write!(f, "There are no walls."),
+        }
+    }
+}
+
+impl Error for BattleDefinitionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::Utf8(error) => Some(error),
+            Self::Layout { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for BattleDefinitionError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<FromUtf8Error> for BattleDefinitionError {
+    fn from(error: FromUtf8Error) -> Self {
+        Self::Utf8(error)
+    }
+}
+
+#[derive(Default)]
+struct BattleDefinitionLoader;
+
+impl AssetLoader for BattleDefinitionLoader {
+    type Asset = BattleDefinition;
+    type Settings = ();
+    type Error = BattleDefinitionError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes).await?;
+        BattleDefinition::parse(&String::from_utf8(bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["battle"]
+    }
+}
+
+/// The battle that will be spawned when entering BattleState::Intro.
+#[derive(Resource)]
+pub struct CurrentBattle(pub Handle<BattleDefinition>);
+
+/// Temp. There is nothing to choose a battle yet, so always load the experiment.
+fn load(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(CurrentBattle(
+        asset_server.load("battles/experiment.battle"),
+    ));
+}
+
+/// Spawns the current battle once it has loaded.
+fn spawn(
+    mut commands: Commands,
+    current_battle: Res<CurrentBattle>,
+    definitions: Res<Assets<BattleDefinition>>,
+    asset_server: Res<AssetServer>,
+    player: Query<(), With<PlayerGrid>>,
+) {
+    if !player.is_empty() {
+        return;
+    }
+    let Some(definition) = definitions.get(&current_battle.0) else {
+        if let LoadState::Failed(error) = asset_server.load_state(&current_battle.0) {
+            error_once!("Could not load the battle: {error}");
+        }
+        return;
+    };
+
+    let mut spawners = layout_spawners(&mut commands, &definition.player);
+    let constraints = definition.selector.clone();
+    let selector = Selector::on_grid();
+    spawners.insert(
+        "s".to_owned(),
+        Box::new(move |commands: &mut Commands| {
+            let entity = selector(commands);
+            commands.entity(entity).insert(constraints.clone());
+            entity
+        }),
+    );
+    let player = match definition.player.spawn(&mut commands, &spawners) {
+        Ok(player) => player,
+        Err(error) => {
+            error_once!("Could not spawn the player grid: {error}");
+            return;
+        }
+    };
+    commands.entity(player).insert(PlayerGrid);
+    commands.spawn(Health::new(definition.health));
+    commands.spawn(OpponentGridSpeed(1.));
+    commands.spawn(WaveSequencer::new(definition.waves.clone()));
+}
+
+/// Has the current battle's definition changed on disk.
+fn modified(
+    mut asset_event: EventReader<AssetEvent<BattleDefinition>>,
+    current_battle: Res<CurrentBattle>,
+) -> bool {
+    // Don't stop early, or the rest of the events will be read next frame.
+    asset_event
+        .read()
+        .filter(|asset_event| asset_event.is_modified(&current_battle.0))
+        .count()
+        > 0
+}
+
+/// Restarts the battle with the new definition, so layout tweaks can be seen without restarting the game.
+/// The camera isn't from the battle, so it stays where it is.
+fn reload(mut next_state: ResMut<NextState<BattleState>>) {
+    info!("The battle's definition changed. Restarting the battle.");
+    // If we are already in the intro, the battle will just be spawned again.
+    next_state.set(BattleState::Intro);
+}
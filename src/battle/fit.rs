@@ -0,0 +1,96 @@
+use super::{PlayerGrid, body::Body, grid::Grid, opponent::CollisionEvent};
+use bevy::prelude::*;
+
+pub fn plugin(app: &mut App) {
+    app.add_systems(Update, check_fit).add_event::<FitEvent>();
+}
+
+/// Bodies can't pass through a cell containing one of these.
+#[derive(Component, Default)]
+pub struct Solid;
+
+/// How a body fared against an opponent grid.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Fit {
+    /// The body lined up with a cell with nothing solid in it.
+    Hole,
+    /// The body lined up with a solid cell.
+    Wall,
+    /// The body didn't line up with the opponent grid at all, so it went around it.
+    Outside,
+}
+
+/// Sent for every body on the player grid when an opponent grid collides with it.
+#[derive(Event, Debug)]
+pub struct FitEvent {
+    pub opponent: Entity,
+    pub body: Entity,
+    pub fit: Fit,
+}
+
+/// Works out which bodies made it through the opponent grid.
+fn check_fit(
+    mut collision_event: EventReader<CollisionEvent>,
+    mut fit_event: EventWriter<FitEvent>,
+    player: Option<Single<Entity, With<PlayerGrid>>>,
+    grids: Query<(&Grid, &Transform)>,
+    bodies: Query<(), With<Body>>,
+    solid: Query<(), With<Solid>>,
+) {
+    let Some(player) = player else {
+        return;
+    };
+    let Ok((player_grid, player_transform)) = grids.get(*player) else {
+        error!("The player grid has no grid.");
+        return;
+    };
+
+    collision_event.read().for_each(|collision_event| {
+        let Ok((opponent_grid, opponent_transform)) = grids.get(collision_event.opponent) else {
+            error!("An opponent grid that collided has no grid.");
+            return;
+        };
+
+        player_grid
+            .cells()
+            .iter()
+            .enumerate()
+            .for_each(|(index, cell)| {
+                let Some(translation) = player_grid.index_to_translation(index) else {
+                    error!("Index outside of grid.");
+                    return;
+                };
+
+                // Line the cell up with the opponent grid, ignoring how far apart they are.
+                let world = player_grid.translation_to_world(player_transform, translation);
+                let fit = match opponent_grid.world_to_translation(opponent_transform, world) {
+                    None => Fit::Outside,
+                    Some(opponent_translation) => {
+                        let Some(opponent_index) =
+                            opponent_grid.translation_to_index(opponent_translation)
+                        else {
+                            error!("Translation is outside the grid.");
+                            return;
+                        };
+
+                        let opponent_cell = &opponent_grid.cells()[opponent_index];
+                        if opponent_cell.iter().any(|entity| solid.contains(*entity)) {
+                            Fit::Wall
+                        } else {
+                            Fit::Hole
+                        }
+                    }
+                };
+
+                cell.iter()
+                    .filter(|entity| bodies.contains(**entity))
+                    .for_each(|body| {
+                        fit_event.send(FitEvent {
+                            opponent: collision_event.opponent,
+                            body: *body,
+                            fit,
+                        });
+                    });
+            });
+    });
+}
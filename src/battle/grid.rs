@@ -1,6 +1,8 @@
 use crate::battle::FromBattle;
 use bevy::{math::U8Vec2, prelude::*};
+use std::{error::Error, fmt::Display};
 
+pub mod layout;
 pub mod on_grid;
 
 pub fn plugin(app: &mut App) {
@@ -9,7 +11,7 @@ pub fn plugin(app: &mut App) {
 }
 
 #[derive(Component)]
-#[require(Transform, FromBattle)]
+#[require(Transform, Visibility, FromBattle)]
 pub struct Grid {
     cells: Box<[Vec<Entity>]>,
     size: U8Vec2,
@@ -18,20 +20,11 @@ pub struct Grid {
 impl Grid {
     pub fn new(size: U8Vec2) -> Self {
         Self {
-            cells: vec![Vec::new(); (size.x * size.y).into()].into_boxed_slice(),
+            cells: vec![Vec::new(); size.x as usize * size.y as usize].into_boxed_slice(),
             size,
         }
     }
 
-    /// Creates a new grid with the cells set.
-    /// Will error if cells' length does not match size.
-    pub fn new_with_cells(cells: Box<[Vec<Entity>]>, size: U8Vec2) -> Self {
-        if cells.len() as u16 != size.x as u16 * size.y as u16 {
-            error!("Size does not match length!");
-        }
-        Self { cells, size }
-    }
-
     pub fn size(&self) -> U8Vec2 {
         self.size
     }
@@ -65,11 +58,61 @@ impl Grid {
             return None;
         }
 
-        let index = translation.y * self.size.x + translation.x;
-        Some(index as usize)
+        let index = translation.y as usize * self.size.x as usize + translation.x as usize;
+        Some(index)
+    }
+
+    /// Converts a translation in grid space to the centre of that cell, relative to the grid's transform.
+    pub fn translation_to_local(&self, translation: U8Vec2) -> Vec2 {
+        translation.as_vec2() + 0.5 - self.size.as_vec2() * 0.5
     }
+
+    /// Converts a position relative to the grid's transform to the translation of the cell it is in.
+    /// Returns None if the position is outside the grid.
+    pub fn local_to_translation(&self, local: Vec2) -> Option<U8Vec2> {
+        let translation = (local + self.size.as_vec2() * 0.5).floor();
+        if translation.cmplt(Vec2::ZERO).any() || translation.cmpge(self.size.as_vec2()).any() {
+            return None;
+        }
+
+        Some(translation.as_u8vec2())
+    }
+
+    /// Converts a translation in grid space to the centre of that cell in world space.
+    pub fn translation_to_world(&self, transform: &Transform, translation: U8Vec2) -> Vec3 {
+        transform.translation
+            + transform.rotation * self.translation_to_local(translation).extend(0.)
+    }
+
+    /// Converts a position in world space to the translation of the cell it is in.
+    /// The position is projected onto the grid's plane, so how far in front or behind the grid it is doesn't matter.
+    /// Returns None if the position is outside the grid.
+    pub fn world_to_translation(&self, transform: &Transform, world: Vec3) -> Option<U8Vec2> {
+        let local = transform.rotation.inverse() * (world - transform.translation);
+        self.local_to_translation(local.truncate())
+    }
+}
+
+#[derive(Debug)]
+pub enum GridError {
+    /// The amount of cells did not match the size of the grid.
+    SizeMismatch { length: usize, size: U8Vec2 },
 }
 
+impl Display for GridError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SizeMismatch { length, size } => write!(
+                f,
+                "There are {length} cells, but the grid is {}x{}.",
+                size.x, size.y
+            ),
+        }
+    }
+}
+
+impl Error for GridError {}
+
 // Debug gizmos for the grid.
 fn debug(mut gizmos: Gizmos, grids: Query<(&Grid, &Transform)>) {
     grids.iter().for_each(|(grid, transform)| {
@@ -87,6 +130,51 @@ fn debug(mut gizmos: Gizmos, grids: Query<(&Grid, &Transform)>) {
     });
 }
 
+/// Spawns a grid, putting every entity in the cells on it.
+/// Those entities become children of the grid, so they move with it.
+/// Errors without spawning the grid if cells' length does not match size.
+pub fn spawn(
+    commands: &mut Commands,
+    cells: Box<[Vec<Entity>]>,
+    size: U8Vec2,
+) -> Result<Entity, GridError> {
+    if cells.len() != size.x as usize * size.y as usize {
+        return Err(GridError::SizeMismatch {
+            length: cells.len(),
+            size,
+        });
+    }
+
+    let grid = Grid::new(size);
+    let grid_entity = commands.spawn_empty().id();
+    let on_grid = cells
+        .iter()
+        .enumerate()
+        .flat_map(|(index, cell)| {
+            let translation = grid.index_to_translation(index).unwrap();
+            cell.iter().map(move |entity| {
+                (
+                    *entity,
+                    on_grid::OnGrid::new(grid_entity, index, translation),
+                )
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // The grid has to exist before anything is put on it, so OnGrid's hooks can add them to their cells.
+    commands.entity(grid_entity).insert(grid);
+    on_grid.into_iter().for_each(|(entity, on_grid)| {
+        commands.entity(entity).insert(on_grid);
+    });
+
+    commands.entity(grid_entity).add_children(&cells.concat());
+    Ok(grid_entity)
+}
+
+#[allow(
+    dead_code,
+    reason = "Only create_grid! uses this, and battles are loaded from files instead."
+)]
 pub trait ToGridCell {
     fn to_grid_cell(self) -> Vec<Entity>;
 }
@@ -124,18 +212,8 @@ macro_rules! create_grid {
             ),*];
 
             let size = U8Vec2::new(rows[0].len() as u8, rows.len() as u8);
-            let grid = Grid::new_with_cells(rows.into_iter().flatten().collect(), size);
-            let grid_entity = $commands.spawn_empty().id();
-
-            grid.cells().iter().enumerate().for_each(|(index, cell)| {
-                let translation = grid.index_to_translation(index).unwrap();
-
-                cell.iter().for_each(|entity| {
-                    $commands.entity(*entity).insert(crate::battle::grid::on_grid::OnGrid::new(grid_entity, index, translation));
-                });
-            });
-
-            $commands.entity(grid_entity).insert(grid);
+            // Every row is an array, so they can't have different lengths.
+            crate::battle::grid::spawn(&mut $commands, rows.into_iter().flatten().collect(), size).unwrap()
         }
     };
 }
@@ -0,0 +1,253 @@
+use super::GridError;
+use bevy::{math::U8Vec2, prelude::*};
+use std::{collections::HashMap, error::Error, fmt::Display};
+
+/// What each symbol in a layout spawns, like the spawners bound to identifiers in the create_grid! macro.
+pub type Spawners = HashMap<String, Box<dyn Fn(&mut Commands) -> Entity + Send + Sync>>;
+
+/// Parses and spawns a grid at runtime, the same way the create_grid! macro does at compile time.
+/// Returns the grid entity.
+#[cfg_attr(
+    not(test),
+    allow(
+        dead_code,
+        reason = "Battles are parsed by their loader, so only the tests spawn straight from text."
+    )
+)]
+pub fn spawn(
+    commands: &mut Commands,
+    text: &str,
+    spawners: &Spawners,
+) -> Result<Entity, LayoutError> {
+    Layout::parse(text)?.spawn(commands, spawners)
+}
+
+/// The symbols in every cell of a grid, written the same way as the create_grid! macro.
+/// Each line is a row, with cells separated by `|`. The square brackets around a row are optional.
+/// ```text
+/// [s| | ]
+/// [ |b|b]
+/// ```
+#[derive(Clone, Debug)]
+pub struct Layout {
+    /// Flattened the same way as the grid's cells.
+    cells: Box<[Option<String>]>,
+    size: U8Vec2,
+}
+
+impl Layout {
+    pub fn parse(text: &str) -> Result<Self, LayoutError> {
+        let rows = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                let line = line.strip_prefix('[').unwrap_or(line);
+                let line = line.strip_suffix(']').unwrap_or(line);
+                line.split('|')
+                    .map(|symbol| {
+                        let symbol = symbol.trim();
+                        (!symbol.is_empty()).then(|| symbol.to_owned())
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let Some(width) = rows.first().map(Vec::len) else {
+            return Err(LayoutError::Empty);
+        };
+        if let Some((row, found)) = rows
+            .iter()
+            .map(Vec::len)
+            .enumerate()
+            .find(|(_, length)| *length != width)
+        {
+            return Err(LayoutError::Ragged {
+                row,
+                expected: width,
+                found,
+            });
+        }
+
+        let (Ok(x), Ok(y)) = (u8::try_from(width), u8::try_from(rows.len())) else {
+            return Err(LayoutError::TooBig);
+        };
+
+        Ok(Self {
+            cells: rows.into_iter().flatten().collect(),
+            size: U8Vec2::new(x, y),
+        })
+    }
+
+    pub fn size(&self) -> U8Vec2 {
+        self.size
+    }
+
+    /// The symbol in each cell, or None if the cell is empty.
+    pub fn cells(&self) -> &[Option<String>] {
+        &self.cells
+    }
+
+    /// Returns the first symbol without a spawner.
+    pub fn unknown_symbol(&self, spawners: &Spawners) -> Option<&str> {
+        self.cells
+            .iter()
+            .flatten()
+            .find(|symbol| !spawners.contains_key(*symbol))
+            .map(String::as_str)
+    }
+
+    /// Spawns the grid, using the spawner for each symbol.
+    /// Nothing is spawned if any symbol has no spawner.
+    /// Returns the grid entity.
+    pub fn spawn(
+        &self,
+        commands: &mut Commands,
+        spawners: &Spawners,
+    ) -> Result<Entity, LayoutError> {
+        if let Some(symbol) = self.unknown_symbol(spawners) {
+            return Err(LayoutError::UnknownSymbol(symbol.to_owned()));
+        }
+
+        let cells = self
+            .cells
+            .iter()
+            .map(|symbol| {
+                symbol
+                    .as_ref()
+                    .map(|symbol| spawners[symbol](commands))
+                    .into_iter()
+                    .collect()
+            })
+            .collect();
+        super::spawn(commands, cells, self.size).map_err(LayoutError::Grid)
+    }
+}
+
+#[derive(Debug)]
+pub enum LayoutError {
+    /// There were no rows.
+    Empty,
+    /// A row had a different amount of cells to the first row.
+    Ragged {
+        row: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// A grid can't be more than 255 cells wide or tall.
+    TooBig,
+    /// A symbol had no spawner.
+    UnknownSymbol(String),
+    Grid(GridError),
+}
+
+impl Display for LayoutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => write!(f, "The layout has no rows."),
+            Self::Ragged {
+                row,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Row {row} has {found} cells, but the first row has {expected}."
+            ),
+            Self::TooBig => write!(f, "The layout is more than 255 cells wide or tall."),
+            Self::UnknownSymbol(symbol) => write!(f, "Unknown symbol \"{symbol}\"."),
+            Self::Grid(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl Error for LayoutError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Grid(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::battle::grid::Grid;
+
+    #[test]
+    fn brackets_are_optional() {
+        let layout = Layout::parse("[s| | ]\n |b|b").unwrap();
+        assert_eq!(layout.size(), U8Vec2::new(3, 2));
+        assert_eq!(
+            layout.cells(),
+            [
+                Some("s".to_owned()),
+                None,
+                None,
+                None,
+                Some("b".to_owned()),
+                Some("b".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn empty() {
+        assert!(matches!(Layout::parse(""), Err(LayoutError::Empty)));
+        assert!(matches!(Layout::parse("\n  \n"), Err(LayoutError::Empty)));
+    }
+
+    #[test]
+    fn ragged() {
+        assert!(matches!(
+            Layout::parse("[s|b]\n[b]"),
+            Err(LayoutError::Ragged {
+                row: 1,
+                expected: 2,
+                found: 1,
+            })
+        ));
+    }
+
+    #[test]
+    fn too_big() {
+        let row = |width: usize| vec![" "; width].join("|");
+        assert_eq!(
+            Layout::parse(&row(255)).unwrap().size(),
+            U8Vec2::new(255, 1)
+        );
+        assert!(matches!(Layout::parse(&row(256)), Err(LayoutError::TooBig)));
+
+        let rows = |height: usize| vec!["[ ]"; height].join("\n");
+        assert_eq!(
+            Layout::parse(&rows(255)).unwrap().size(),
+            U8Vec2::new(1, 255)
+        );
+        assert!(matches!(
+            Layout::parse(&rows(256)),
+            Err(LayoutError::TooBig)
+        ));
+    }
+
+    #[test]
+    fn spawns_each_symbol() {
+        let mut world = World::new();
+        let mut spawners = Spawners::new();
+        spawners.insert(
+            "s".to_owned(),
+            Box::new(|commands: &mut Commands| commands.spawn_empty().id()),
+        );
+
+        let grid = spawn(&mut world.commands(), "[s| ]\n[ |s]", &spawners).unwrap();
+        world.flush();
+        let grid = world.get::<Grid>(grid).unwrap();
+        assert_eq!(grid.size(), U8Vec2::new(2, 2));
+        let lengths = grid.cells().iter().map(Vec::len).collect::<Vec<_>>();
+        assert_eq!(lengths, [1, 0, 0, 1]);
+
+        assert!(matches!(
+            spawn(&mut world.commands(), "[w]", &spawners),
+            Err(LayoutError::UnknownSymbol(symbol)) if symbol == "w"
+        ));
+    }
+}
@@ -1,13 +1,85 @@
-use bevy::{math::U8Vec2, prelude::*};
+use bevy::{
+    ecs::{component::ComponentId, world::DeferredWorld},
+    math::U8Vec2,
+    prelude::*,
+};
+use std::f32::consts::PI;
 
 use super::Grid;
+use crate::battle::selector::OnlyOneInCell;
 
 pub fn plugin(app: &mut App) {
-    app.add_systems(Update, transforms);
+    app.init_resource::<GridAnimation>()
+        .add_systems(Update, transforms);
+
+    #[cfg(debug_assertions)]
+    app.add_systems(PostUpdate, validate);
+}
+
+/// How entities on a grid animate when moving between cells.
+/// Only the transform is animated. OnGrid changes straight away, so input stays responsive.
+#[derive(Resource)]
+pub struct GridAnimation {
+    /// How long moving to a new cell takes. At 0, entities snap to their cell.
+    pub seconds: f32,
+    pub easing: Easing,
+    /// How much entities stretch in the direction they are moving, and squash in the others, halfway through moving.
+    /// At 0, entities keep their shape.
+    pub squash_and_stretch: f32,
+}
+
+impl Default for GridAnimation {
+    fn default() -> Self {
+        Self {
+            seconds: 0.08,
+            easing: Easing::EaseOut,
+            squash_and_stretch: 0.15,
+        }
+    }
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Easing {
+    Linear,
+    /// Starts fast and slows down.
+    EaseOut,
+    /// Starts and ends slowly.
+    EaseInOut,
+}
+
+impl Easing {
+    /// Takes progress from 0 to 1, and returns the eased progress from 0 to 1.
+    pub fn ease(self, progress: f32) -> f32 {
+        match self {
+            Self::Linear => progress,
+            Self::EaseOut => 1. - (1. - progress).powi(2),
+            Self::EaseInOut => progress * progress * (3. - 2. * progress),
+        }
+    }
+}
+
+/// Where an entity on a grid is animating from.
 #[derive(Component)]
-#[require(Transform)]
+struct CellAnimation {
+    from: Vec3,
+    /// From 0 to 1. At 1, the animation is done.
+    progress: f32,
+}
+
+impl Default for CellAnimation {
+    fn default() -> Self {
+        Self {
+            from: Vec3::ZERO,
+            progress: 1.,
+        }
+    }
+}
+
+/// Puts an entity in a cell of a grid.
+/// Inserting this adds the entity to the cell, and removing or replacing it takes the entity back out, so the grid's cells always match.
+#[derive(Component)]
+#[require(Transform, CellAnimation)]
+#[component(on_insert = add_to_cell, on_replace = remove_from_cell)]
 pub struct OnGrid {
     grid: Entity,
     /// u16 can fit all indices, due to width and height being u8s.
@@ -65,33 +137,163 @@ impl OnGrid {
 
         new_cell.push(entity);
     }
+
+    /// Moves an entity to a cell on another grid, updating both grids' cells and the entity's parent at once.
+    /// Also works for moving within the same grid.
+    /// Refuses to move it into a cell that already has a matching OnlyOneInCell.
+    /// ```ignore
+    /// commands
+    ///     .entity(body)
+    ///     .queue(OnGrid::transfer(inventory, U8Vec2::ZERO));
+    /// ```
+    #[allow(
+        dead_code,
+        reason = "Nothing moves between grids yet, but inventories and the like will."
+    )]
+    pub fn transfer(grid: Entity, translation: U8Vec2) -> impl FnOnce(EntityWorldMut) {
+        move |mut entity: EntityWorldMut| {
+            let Some(grid_component) = entity.world().get::<Grid>(grid) else {
+                error!("Could not transfer to {grid:?}, as it isn't a grid.");
+                return;
+            };
+            let Some(index) = grid_component.translation_to_index(translation) else {
+                error!("Could not transfer to {translation}, as it is outside the grid.");
+                return;
+            };
+            if let Some(only) = entity.get::<OnlyOneInCell>()
+                && grid_component.cells()[index].iter().any(|other| {
+                    *other != entity.id()
+                        && entity.world().get::<OnlyOneInCell>(*other) == Some(only)
+                })
+            {
+                error!(
+                    "Could not transfer to {translation}, as something like it is already there."
+                );
+                return;
+            }
+
+            // Replacing OnGrid takes the entity out of its old cell and puts it in the new one.
+            entity.insert(OnGrid::new(grid, index, translation));
+            // Keep it where it is in the world, so it animates over to the new grid.
+            entity.set_parent_in_place(grid);
+        }
+    }
+}
+
+fn add_to_cell(mut world: DeferredWorld, entity: Entity, _: ComponentId) {
+    let Some(on_grid) = world.get::<OnGrid>(entity) else {
+        return;
+    };
+    let (grid, index) = (on_grid.grid, on_grid.index());
+
+    let Some(mut grid) = world.get_mut::<Grid>(grid) else {
+        error!("An entity was put on a grid that doesn't exist.");
+        return;
+    };
+    let Some(cell) = grid.cells.get_mut(index) else {
+        error!("An entity was put outside of its grid.");
+        return;
+    };
+    if !cell.contains(&entity) {
+        cell.push(entity);
+    }
 }
 
-/// Sets transforms to be correct based on their grid.
+fn remove_from_cell(mut world: DeferredWorld, entity: Entity, _: ComponentId) {
+    let Some(on_grid) = world.get::<OnGrid>(entity) else {
+        return;
+    };
+    let (grid, index) = (on_grid.grid, on_grid.index());
+
+    // The grid may have been despawned first, in which case there is nothing to do.
+    let Some(mut grid) = world.get_mut::<Grid>(grid) else {
+        return;
+    };
+    let Some(cell) = grid.cells.get_mut(index) else {
+        error!("An entity was outside of its grid.");
+        return;
+    };
+    if let Some(in_cell_index) = cell.iter().position(|in_cell| *in_cell == entity) {
+        cell.swap_remove(in_cell_index);
+    }
+}
+
+/// Checks that every entity on a grid is in the cell it thinks it is in, and that everything in a cell is on that grid.
+#[cfg(debug_assertions)]
+fn validate(on_grid: Query<(Entity, &OnGrid)>, grids: Query<(Entity, &Grid)>) {
+    on_grid.iter().for_each(|(entity, on_grid)| {
+        let Ok((_, grid)) = grids.get(on_grid.grid) else {
+            error!("{entity:?} is on {:?}, which isn't a grid.", on_grid.grid);
+            return;
+        };
+        if grid.translation_to_index(on_grid.translation) != Some(on_grid.index()) {
+            error!(
+                "{entity:?} has index {} but translation {}.",
+                on_grid.index, on_grid.translation
+            );
+        }
+        if !grid
+            .cells
+            .get(on_grid.index())
+            .is_some_and(|cell| cell.contains(&entity))
+        {
+            error!(
+                "{entity:?} is not in cell {} of {:?}.",
+                on_grid.index, on_grid.grid
+            );
+        }
+    });
+
+    grids.iter().for_each(|(grid_entity, grid)| {
+        grid.cells.iter().enumerate().for_each(|(index, cell)| {
+            cell.iter().for_each(|entity| match on_grid.get(*entity) {
+                Ok((_, on_grid)) if on_grid.grid == grid_entity && on_grid.index() == index => {}
+                _ => error!("{entity:?} is in cell {index} of {grid_entity:?}, but isn't on it."),
+            });
+        });
+    });
+}
+
+/// Sets transforms to be correct based on their grid, animating them to their new cell when they move.
+/// Entities on a grid are its children, so these are relative to the grid, and follow it when it moves.
 fn transforms(
-    mut transforms: Query<(Ref<OnGrid>, &mut Transform)>,
-    grids: Query<(&Transform, &Grid), Without<OnGrid>>,
+    mut transforms: Query<(Ref<OnGrid>, &mut Transform, &mut CellAnimation)>,
+    grids: Query<&Grid>,
+    grid_animation: Res<GridAnimation>,
+    time: Res<Time>,
 ) {
+    let time_delta_seconds = time.delta_secs();
+
     transforms
         .par_iter_mut()
-        .for_each(|(on_grid, mut transform)| {
-            if !on_grid.is_changed() {
+        .for_each(|(on_grid, mut transform, mut animation)| {
+            if on_grid.is_changed() {
+                animation.from = transform.translation;
+                // Entities that have only just been put on a grid have nowhere to animate from.
+                animation.progress = if on_grid.is_added() { 1. } else { 0. };
+            } else if animation.progress >= 1. {
                 return;
             }
             // info!("Updating grid translation!");
 
-            let Ok((grid_transform, grid)) = grids.get(on_grid.grid) else {
+            let Ok(grid) = grids.get(on_grid.grid) else {
                 return;
             };
 
-            // Set each transform to be in the correct 2d translation, and then rotate it around the grid's translation with the grid's rotation.
-            transform.translation = grid_transform.translation
-                - (grid.size.as_vec2() * 0.5).extend(0.)
-                + Vec3::new(
-                    on_grid.translation.x as f32 + 0.5,
-                    on_grid.translation.y as f32 + 0.5,
-                    0.,
-                );
-            transform.rotate_around(grid_transform.translation, grid_transform.rotation);
+            animation.progress = if grid_animation.seconds > 0. {
+                (animation.progress + time_delta_seconds / grid_animation.seconds).min(1.)
+            } else {
+                1.
+            };
+            let eased = grid_animation.easing.ease(animation.progress);
+
+            let to = grid.translation_to_local(on_grid.translation).extend(0.);
+            transform.translation = animation.from.lerp(to, eased);
+
+            // Stretch along the direction of movement.
+            let stretch = grid_animation.squash_and_stretch * (animation.progress * PI).sin();
+            let direction = (to - animation.from).normalize_or_zero().abs();
+            transform.scale =
+                Vec3::ONE + direction * stretch - (Vec3::ONE - direction) * stretch * 0.5;
         });
 }
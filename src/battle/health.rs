@@ -0,0 +1,115 @@
+use super::{
+    FromBattle,
+    fit::{Fit, FitEvent},
+    piece::PieceOf,
+};
+use bevy::prelude::*;
+
+pub fn plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        (damage_unfit_bodies, decrease_invulnerability, take_damage).chain(),
+    )
+    .add_event::<DamageEvent>()
+    .add_event::<DefeatEvent>();
+}
+
+/// How long the player can't be damaged for after taking damage.
+const INVULNERABLE_SECONDS: f32 = 1.;
+
+/// The player's health.
+/// Singleton.
+#[derive(Component)]
+#[require(FromBattle)]
+pub struct Health {
+    current: u8,
+    max: u8,
+    /// While above 0, damage is ignored.
+    invulnerable_seconds: f32,
+}
+
+impl Health {
+    pub fn new(max: u8) -> Self {
+        Self {
+            current: max,
+            max,
+            invulnerable_seconds: 0.,
+        }
+    }
+
+    pub fn invulnerable(&self) -> bool {
+        self.invulnerable_seconds > 0.
+    }
+}
+
+/// When a body damages the player.
+#[derive(Event, Debug)]
+pub struct DamageEvent {
+    pub amount: u8,
+}
+
+/// When the player's health reaches 0.
+#[derive(Event, Debug)]
+pub struct DefeatEvent;
+
+/// Every body that hit a wall damages the player.
+/// A piece only damages the player once per wall, no matter how many of its bodies hit.
+fn damage_unfit_bodies(
+    mut fit_event: EventReader<FitEvent>,
+    mut damage_event: EventWriter<DamageEvent>,
+    pieces: Query<&PieceOf>,
+) {
+    let mut damaged_by_pieces = vec![];
+
+    fit_event.read().for_each(|fit_event| {
+        let Fit::Wall = fit_event.fit else {
+            return;
+        };
+
+        if let Ok(piece_of) = pieces.get(fit_event.body) {
+            if damaged_by_pieces.contains(&(fit_event.opponent, *piece_of)) {
+                return;
+            }
+            damaged_by_pieces.push((fit_event.opponent, *piece_of));
+        }
+
+        damage_event.send(DamageEvent { amount: 1 });
+    });
+}
+
+fn decrease_invulnerability(health: Option<Single<&mut Health>>, time: Res<Time>) {
+    let Some(mut health) = health else {
+        return;
+    };
+    health.invulnerable_seconds = (health.invulnerable_seconds - time.delta_secs()).max(0.);
+}
+
+/// Applies all the damage from this frame at once, so every body that hit the same wall counts.
+/// Only after that does invulnerability start.
+fn take_damage(
+    mut damage_event: EventReader<DamageEvent>,
+    mut defeat_event: EventWriter<DefeatEvent>,
+    health: Option<Single<&mut Health>>,
+) {
+    let damage = damage_event.read().fold(0_u8, |damage, damage_event| {
+        damage.saturating_add(damage_event.amount)
+    });
+
+    let Some(mut health) = health else {
+        return;
+    };
+    if damage == 0 || health.current == 0 || health.invulnerable() {
+        return;
+    }
+
+    health.current = health.current.saturating_sub(damage);
+    health.invulnerable_seconds = INVULNERABLE_SECONDS;
+    info!(
+        "Took {damage} damage. {}/{} health left.",
+        health.current, health.max
+    );
+
+    if health.current == 0 {
+        defeat_event.send(DefeatEvent);
+    }
+}
@@ -1,32 +1,65 @@
+use super::{FromBattle, state::BattleState};
 use bevy::prelude::*;
+use speed::SpeedProfile;
+
+pub mod speed;
 
 pub fn plugin(app: &mut App) {
-    app.add_systems(Update, move_grids)
-        .add_event::<CollisionEvent>();
+    app.add_systems(
+        Update,
+        move_grids.run_if(in_state(BattleState::Dodging).or(in_state(BattleState::Resolving))),
+    )
+    .add_event::<CollisionEvent>()
+    .add_event::<WallPassedEvent>();
 }
 
+/// How far behind the player an opponent grid goes before it is despawned.
+const PASSED_DISTANCE: f32 = 30.;
+
 /// A grid belonging to the opponent.
-#[derive(Component, Default)]
+#[derive(Component)]
 pub struct OpponentGrid {
+    speed: SpeedProfile,
+    /// How long it has existed for, affected by OpponentGridSpeed.
+    seconds: f32,
     /// Has the opponent grid collided yet.
     collided: bool,
 }
 
+impl OpponentGrid {
+    pub fn new(speed: SpeedProfile) -> Self {
+        Self {
+            speed,
+            seconds: 0.,
+            collided: false,
+        }
+    }
+}
+
 /// When an opponent grid collides with the player grid.
 #[derive(Event)]
 pub struct CollisionEvent {
     pub opponent: Entity,
 }
 
-/// Controls the movement speed of all OpponentGrids.
+/// When an opponent grid is far enough behind the player to be despawned.
+/// The opponent grid is despawned at the same time as this is sent, so there is no entity to go with it.
+#[derive(Event)]
+pub struct WallPassedEvent;
+
+/// Multiplies how fast time passes for all OpponentGrids, so it affects their speed profiles too.
+/// Useful for difficulty and slow motion.
 /// Singleton.
 #[derive(Component)]
+#[require(FromBattle)]
 pub struct OpponentGridSpeed(pub f32);
 
 fn move_grids(
+    mut commands: Commands,
     mut grid: Query<(&mut Transform, &mut OpponentGrid, Entity)>,
     speed: Option<Single<&OpponentGridSpeed>>,
     mut collision_event: EventWriter<CollisionEvent>,
+    mut wall_passed_event: EventWriter<WallPassedEvent>,
     time: Res<Time>,
 ) {
     let Some(speed) = speed.map(|speed| speed.0) else {
@@ -36,19 +69,23 @@ fn move_grids(
     let time_delta_seconds = time.delta_secs();
 
     grid.iter_mut()
-        .for_each(|(mut transform, mut grid, entity)| {
-            if transform.translation.z <= -30. {
-                //info!("Done!");
-                return;
-            }
-
-            transform.translation.z -= time_delta_seconds * speed;
+        .for_each(|(mut transform, mut opponent_grid, entity)| {
+            let time_delta_seconds = time_delta_seconds * speed;
+            opponent_grid.seconds += time_delta_seconds;
+            transform.translation.z -=
+                time_delta_seconds * opponent_grid.speed.speed(opponent_grid.seconds);
 
-            if transform.translation.z <= 0. && !grid.collided {
-                grid.collided = true;
+            if transform.translation.z <= 0. && !opponent_grid.collided {
+                opponent_grid.collided = true;
                 //info!("Collided!");
 
                 collision_event.send(CollisionEvent { opponent: entity });
             }
+
+            if transform.translation.z <= -PASSED_DISTANCE {
+                wall_passed_event.send(WallPassedEvent);
+                // Everything on the grid is a child of it, so it is despawned too.
+                commands.entity(entity).despawn_recursive();
+            }
         });
 }
@@ -0,0 +1,122 @@
+use std::{f32::consts::PI, str::FromStr};
+
+/// How fast an opponent grid approaches, over time.
+/// Written in battle definitions as a number for a constant speed, or as `kind:arguments`, like `lunge:1,8,2,0.25`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SpeedProfile {
+    /// `3`
+    Constant(f32),
+    /// Starts at initial, gaining acceleration every second until it reaches max.
+    /// `accelerate:initial,acceleration,max`
+    Accelerating {
+        initial: f32,
+        acceleration: f32,
+        max: f32,
+    },
+    /// Smoothly changes from one speed to another over some seconds.
+    /// `ease:from,to,seconds`
+    EaseInOut { from: f32, to: f32, seconds: f32 },
+    /// Moves for some seconds, then stops for some seconds, forever.
+    /// `stop:speed,moving,stopped`
+    StopAndGo {
+        speed: f32,
+        moving: f32,
+        stopped: f32,
+    },
+    /// Moves normally, but suddenly lunges forward every so often.
+    /// `lunge:speed,lunge_speed,every,lunge_seconds`
+    Lunge {
+        speed: f32,
+        lunge_speed: f32,
+        every: f32,
+        lunge_seconds: f32,
+    },
+}
+
+impl SpeedProfile {
+    /// The speed after the opponent grid has existed for some seconds.
+    pub fn speed(&self, seconds: f32) -> f32 {
+        match *self {
+            Self::Constant(speed) => speed,
+            Self::Accelerating {
+                initial,
+                acceleration,
+                max,
+            } => (initial + acceleration * seconds).min(max),
+            Self::EaseInOut {
+                from,
+                to,
+                seconds: duration,
+            } => {
+                let progress = if duration > 0. {
+                    (seconds / duration).clamp(0., 1.)
+                } else {
+                    1.
+                };
+                // Goes from 0 to 1, starting and ending slowly.
+                let eased = (1. - (progress * PI).cos()) * 0.5;
+                from + (to - from) * eased
+            }
+            Self::StopAndGo {
+                speed,
+                moving,
+                stopped,
+            } => {
+                if moving + stopped <= 0. || seconds.rem_euclid(moving + stopped) < moving {
+                    speed
+                } else {
+                    0.
+                }
+            }
+            Self::Lunge {
+                speed,
+                lunge_speed,
+                every,
+                lunge_seconds,
+            } => {
+                // The first lunge is after every seconds, not straight away.
+                if every > 0. && seconds >= every && seconds.rem_euclid(every) < lunge_seconds {
+                    lunge_speed
+                } else {
+                    speed
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for SpeedProfile {
+    type Err = ();
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let Some((kind, arguments)) = text.split_once(':') else {
+            return text.parse().map(Self::Constant).map_err(|_| ());
+        };
+        let arguments = arguments
+            .split(',')
+            .map(|argument| argument.trim().parse::<f32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| ())?;
+
+        match (kind, arguments.as_slice()) {
+            ("accelerate", &[initial, acceleration, max]) => Ok(Self::Accelerating {
+                initial,
+                acceleration,
+                max,
+            }),
+            ("ease", &[from, to, seconds]) => Ok(Self::EaseInOut { from, to, seconds }),
+            ("stop", &[speed, moving, stopped]) => Ok(Self::StopAndGo {
+                speed,
+                moving,
+                stopped,
+            }),
+            ("lunge", &[speed, lunge_speed, every, lunge_seconds]) => Ok(Self::Lunge {
+                speed,
+                lunge_speed,
+                every,
+                lunge_seconds,
+            }),
+            _ => Err(()),
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use super::FromBattle;
+use bevy::{
+    ecs::{component::ComponentId, world::DeferredWorld},
+    prelude::*,
+};
+
+/// Bodies that move together as one rigid shape, like a domino or a tetromino.
+/// They also only count once when hitting a wall.
+#[derive(Component, Default)]
+#[require(FromBattle)]
+pub struct Piece {
+    members: Vec<Entity>,
+}
+
+impl Piece {
+    pub fn members(&self) -> &[Entity] {
+        &self.members
+    }
+}
+
+/// Makes a body part of a piece.
+/// Inserting this adds the body to the piece's members, and removing or replacing it takes the body back out.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+#[component(on_insert = add_to_piece, on_replace = remove_from_piece)]
+pub struct PieceOf(pub Entity);
+
+fn add_to_piece(mut world: DeferredWorld, entity: Entity, _: ComponentId) {
+    let Some(piece_of) = world.get::<PieceOf>(entity).copied() else {
+        return;
+    };
+    let Some(mut piece) = world.get_mut::<Piece>(piece_of.0) else {
+        error!("A body was put in a piece that doesn't exist.");
+        return;
+    };
+    if !piece.members.contains(&entity) {
+        piece.members.push(entity);
+    }
+}
+
+fn remove_from_piece(mut world: DeferredWorld, entity: Entity, _: ComponentId) {
+    let Some(piece_of) = world.get::<PieceOf>(entity).copied() else {
+        return;
+    };
+    // The piece may have been despawned first, in which case there is nothing to do.
+    let Some(mut piece) = world.get_mut::<Piece>(piece_of.0) else {
+        return;
+    };
+    piece.members.retain(|member| *member != entity);
+}
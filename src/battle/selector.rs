@@ -1,15 +1,31 @@
-use std::any::TypeId;
+use std::{any::TypeId, collections::VecDeque};
 
 use super::{
-    CubeMesh,
+    CubeMesh, FromBattle,
     grid::{Grid, on_grid::OnGrid},
+    piece::{Piece, PieceOf},
+    state::BattleState,
 };
 use crate::actions::{Action, Actions};
-use bevy::prelude::*;
+use bevy::{math::U8Vec2, prelude::*};
+use constraints::SelectorConstraints;
+use pointer::{PointerPress, PointerTarget};
+use settings::{MovementSettings, Simultaneous};
+
+pub mod constraints;
+mod pointer;
+pub mod settings;
 
 pub fn plugin(app: &mut App) {
+    constraints::plugin(app);
+    pointer::plugin(app);
+    settings::plugin(app);
     app.add_systems(PreStartup, selector_material)
-        .add_systems(Update, (movement, increase_time_since_last_move));
+        .add_systems(
+            Update,
+            movement.run_if(in_state(BattleState::Dodging).or(in_state(BattleState::Resolving))),
+        )
+        .add_systems(Update, draw_selection);
 }
 
 #[derive(Resource)]
@@ -27,6 +43,17 @@ fn selector_material(world: &mut World) {
     world.insert_resource(material);
 }
 
+/// The movement actions, and which way they move the selector.
+const DIRECTIONS: [(Action, IVec2); 4] = [
+    (Action::Up, IVec2::Y),
+    (Action::Down, IVec2::NEG_Y),
+    (Action::Left, IVec2::X),
+    (Action::Right, IVec2::NEG_X),
+];
+
+/// The most moves that can be buffered at once.
+const BUFFER_LENGTH: usize = 4;
+
 #[derive(Component, Default)]
 pub struct Pullable;
 
@@ -36,13 +63,37 @@ pub struct OnlyOneInCell(pub TypeId);
 
 /// The players selection.
 #[derive(Component, Default)]
+#[require(FromBattle)]
 pub struct Selector {
     /// Will pull the tile it is on with it.
     pub pull: bool,
     /// Can the player toggle pull?
     pub pull_locked: bool,
+    /// Pulling also pulls every body connected to what is pulled.
+    pub sticky: bool,
+    /// Moving into bodies shoves them along.
+    pub push: bool,
+    /// Cells on the selector's grid selected to move as a group.
+    /// When not empty, pulling and reshaping move everything in these cells, instead of what is around the selector.
+    pub selection: Vec<U8Vec2>,
+    /// Where a box selection started, while it is being dragged out.
+    box_start: Option<U8Vec2>,
+
+    /// For each direction, how long until it repeats while held.
+    seconds_until_repeat: [f32; 4],
+    /// The index of the direction pressed most recently.
+    last_pressed: Option<usize>,
+    /// Moves that haven't happened yet, oldest first.
+    buffer: VecDeque<BufferedMove>,
+    /// Cells clicked or dragged through, for the selector to step to in order.
+    targets: VecDeque<PointerTarget>,
+    pointer_press: Option<PointerPress>,
+}
 
-    seconds_since_last_move: f32,
+struct BufferedMove {
+    direction: IVec2,
+    /// How long it has been buffered for.
+    seconds: f32,
 }
 
 impl Selector {
@@ -59,179 +110,523 @@ impl Selector {
     }
 }
 
-/// Increases the time since last move.
-fn increase_time_since_last_move(mut selectors: Query<&mut Selector>, time: Res<Time>) {
-    let time_delta_seconds = time.delta_secs();
-    selectors.iter_mut().for_each(|mut selector| {
-        selector.seconds_since_last_move += time_delta_seconds;
-    });
-}
-
 /// Move the selector.
 fn movement(
-    mut selectors: Query<(Entity, &mut Selector)>,
-    pullable: Query<(Option<&OnlyOneInCell>, Has<Pullable>)>,
+    mut selectors: Query<(Entity, &mut Selector, Option<&SelectorConstraints>)>,
+    pullable: Query<Option<&PieceOf>, With<Pullable>>,
+    only_one: Query<&OnlyOneInCell>,
+    pieces: Query<&Piece>,
     mut on_grid: Query<&mut OnGrid>,
     mut grids: Query<&mut Grid>,
     actions: Actions,
+    settings: Res<MovementSettings>,
+    time: Res<Time>,
 ) {
+    let time_delta_seconds = time.delta_secs();
+
     selectors
         .iter_mut()
-        .for_each(|(selector_entity, mut selector)| {
-            if actions.just_pressed(&Action::Pull) {
+        .for_each(|(selector_entity, mut selector, constraints)| {
+            let pull_locked = selector.pull_locked
+                || constraints.is_some_and(|constraints| constraints.pull.is_some());
+            if actions.just_pressed(&Action::Pull) && !pull_locked {
                 selector.pull = !selector.pull;
             }
+            if actions.just_pressed(&Action::Sticky) {
+                selector.sticky = !selector.sticky;
+            }
+            if actions.just_pressed(&Action::Push) {
+                selector.push = !selector.push;
+            }
 
-            let Ok(mut selector_on_grid) = on_grid.get_mut(selector_entity) else {
+            let Ok(selector_on_grid) = on_grid.get(selector_entity) else {
                 error!("Selector entity is not on grid.");
                 return;
             };
-            let Ok(mut grid) = grids.get_mut(selector_on_grid.entity()) else {
+            let (grid_entity, index, translation) = (
+                selector_on_grid.entity(),
+                selector_on_grid.index(),
+                selector_on_grid.translation(),
+            );
+            let Ok(mut grid) = grids.get_mut(grid_entity) else {
                 error_once!("A selector's grid could not be found.");
                 return;
             };
 
-            let mut direction: Option<(u8, i8)> = None;
-
-            // If multiple keys are pressed, we do nothing.
-            for (action, action_direction) in [
-                (&Action::Up, (1, 1)),
-                (&Action::Down, (1, -1)),
-                (&Action::Left, (0, 1)),
-                (&Action::Right, (0, -1)),
-            ] {
-                if actions.just_pressed(action)
-                    || (selector.seconds_since_last_move > 0.15 && actions.pressed(action))
+            if actions.just_pressed(&Action::Select) {
+                match selector
+                    .selection
+                    .iter()
+                    .position(|cell| *cell == translation)
                 {
-                    selector.seconds_since_last_move = 0.;
-                    match direction {
-                        None => {
-                            direction = Some(action_direction);
-                        }
-                        Some(_) => {
-                            direction = None;
-                            break;
+                    Some(position) => {
+                        selector.selection.swap_remove(position);
+                    }
+                    None => selector.selection.push(translation),
+                }
+            }
+            if actions.just_pressed(&Action::BoxSelect) {
+                selector.box_start = Some(translation);
+            }
+            if actions.just_released(&Action::BoxSelect)
+                && let Some(box_start) = selector.box_start.take()
+            {
+                let (min, max) = (box_start.min(translation), box_start.max(translation));
+                for y in min.y..=max.y {
+                    for x in min.x..=max.x {
+                        let cell = U8Vec2::new(x, y);
+                        if !selector.selection.contains(&cell) {
+                            selector.selection.push(cell);
                         }
                     }
                 }
             }
+            if actions.just_pressed(&Action::Deselect) {
+                selector.selection.clear();
+            }
 
-            let Some(direction) = direction else {
-                return;
+            // Rotate or mirror the selection, or the bodies connected to the selector, around the selector.
+            let reshape: Option<fn(IVec2) -> IVec2> = if actions.just_pressed(&Action::Rotate) {
+                // Clockwise, as the player sees it. Positive x is to the player's left.
+                Some(|offset| IVec2::new(-offset.y, offset.x))
+            } else if actions.just_pressed(&Action::RotateHalf) {
+                Some(|offset| -offset)
+            } else if actions.just_pressed(&Action::Mirror) {
+                Some(|offset| IVec2::new(-offset.x, offset.y))
+            } else {
+                None
             };
-            let mut translation = selector_on_grid.translation();
-            let new_translation = (translation[direction.0 as usize] as i8 + direction.1) as u8;
-
-            if new_translation >= grid.size()[direction.0 as usize] {
-                return;
+            let frozen = constraints.is_some_and(SelectorConstraints::frozen);
+            if let Some(reshape) = reshape
+                && !frozen
+            {
+                let pivot = translation.as_ivec2();
+                let cells = acting_on(&selector.selection, &grid, index);
+                // Without a selection, reshaping always takes everything connected, otherwise it would tear bodies apart.
+                let sticky = selector.selection.is_empty() || selector.sticky;
+                let moves = gather(&cells, sticky, &grid, &pullable, &pieces, &on_grid)
+                    .into_iter()
+                    .filter_map(|entity| {
+                        let current = on_grid.get(entity).ok()?.translation().as_ivec2();
+                        Some((entity, pivot + reshape(current - pivot) - current))
+                    })
+                    .collect::<Vec<_>>();
+                // Reshaping can't take anything outside of the region.
+                let in_region = constraints.is_none_or(|constraints| {
+                    moves.iter().all(|(entity, offset)| {
+                        on_grid.get(*entity).is_ok_and(|entity_on_grid| {
+                            constraints.in_region(entity_on_grid.translation().as_ivec2() + *offset)
+                        })
+                    })
+                });
+                if in_region && try_move(&moves, &mut grid, &mut on_grid, &only_one) {
+                    let size = grid.size();
+                    move_selection(&mut selector.selection, size, |cell| {
+                        pivot + reshape(cell - pivot)
+                    });
+                }
             }
 
-            translation[direction.0 as usize] = new_translation;
-
-            // if selector.pull {
-            //     info!("Start pulling");
-
-            //     let Some(cell) = grid.cells.get(on_grid.index()) else {
-            //         error!("Index outside of grid.");
-            //         return;
-            //     };
-            //     // I dislike allocating heap memory every time we move, but I don't see an alternative.
-            //     let cell = cell.clone();
-
-            //     cell.iter().copied().for_each(|on_grid| {
-            //         let Ok((entity, mut on_grid)) = pullable.get_mut(on_grid) else {
-            //             return;
-            //         };
-
-            //         on_grid.set_translation(&mut grid, entity, translation);
-            //         info!("Pulled.");
-            //     });
-
-            //     let Some(grid_cell) = grid.cells.get_mut(on_grid.index()) else {
-            //         error!("Index outside of grid.");
-            //         return;
-            //     };
-            // }
-            // if selector.pull {
-            //     info!("Start pulling");
-
-            //     let index_original = on_grid.index();
-            //     on_grid.set_translation(&mut grid, entity, translation);
-            //     let index = on_grid.index();
-
-            //     let index_max = index_original.max(index);
-            //     let index_min = index_original.min(index);
-
-            //     // Get both cells mutably and safely.
-            //     let cells = grid.cells.split_at_mut(index_max);
-            //     let Some(cell_1) = cells.0.get_mut(index_min) else {
-            //         error!("Index outside of grid.");
-            //         return;
-            //     };
-            //     let Some(cell_2) = cells.1.get_mut(0) else {
-            //         error!("Index outside of grid.");
-            //         return;
-            //     };
-
-            //     // Not sound. Invalidates indices in OnGrid.
-            //     std::mem::swap(cell_1, cell_2);
-            // } else {
-            //     on_grid.set_translation(&mut grid, entity, translation);
-            // }
-
-            if selector.pull {
-                let index_original = selector_on_grid.index();
-                info!("index_original {index_original}");
-                let Some(index) = grid.translation_to_index(translation) else {
-                    error!("New translation is not in grid.");
-                    return;
-                };
+            // Which directions are pressed or repeating this frame.
+            let mut fired = vec![];
+            for (direction_index, (action, action_direction)) in DIRECTIONS.iter().enumerate() {
+                if constraints
+                    .is_some_and(|constraints| constraints.disabled_directions.contains(action))
+                {
+                    continue;
+                }
 
-                let Some(cell_original) = grid.cells().get(index_original) else {
-                    error!("Original index is not in grid.");
-                    return;
-                };
-                let Some(cell) = grid.cells().get(index) else {
-                    error!("New index is not in grid.");
-                    return;
+                let seconds_until_repeat = &mut selector.seconds_until_repeat[direction_index];
+                let pressed = if actions.just_pressed(action) {
+                    *seconds_until_repeat = settings.initial_delay;
+                    selector.last_pressed = Some(direction_index);
+                    true
+                } else if actions.pressed(action) {
+                    *seconds_until_repeat -= time_delta_seconds;
+                    if *seconds_until_repeat <= 0. {
+                        // Don't build up repeats during a long frame.
+                        *seconds_until_repeat =
+                            (*seconds_until_repeat + settings.repeat_interval).max(0.);
+                        true
+                    } else {
+                        false
+                    }
+                } else {
+                    false
                 };
 
-                let mut pull = vec![selector_entity];
+                if pressed {
+                    fired.push((direction_index, *action_direction));
+                }
+            }
 
-                for in_cell_original_entity in cell_original {
-                    let Ok(in_cell_original) = pullable.get(*in_cell_original_entity) else {
-                        continue;
-                    };
-                    if in_cell_original.1 {
-                        pull.push(*in_cell_original_entity);
+            let new_moves: Vec<IVec2> = match settings.simultaneous {
+                Simultaneous::Queue => fired.into_iter().map(|(_, direction)| direction).collect(),
+                Simultaneous::Cancel => match fired[..] {
+                    [(_, direction)] => vec![direction],
+                    _ => vec![],
+                },
+                Simultaneous::LastPressedWins => {
+                    let last_held = selector
+                        .last_pressed
+                        .filter(|last| actions.pressed(&DIRECTIONS[*last].0));
+                    fired
+                        .into_iter()
+                        .filter(|(direction_index, _)| {
+                            last_held.is_none_or(|last| last == *direction_index)
+                        })
+                        .map(|(_, direction)| direction)
+                        .last()
+                        .into_iter()
+                        .collect()
+                }
+                Simultaneous::Diagonal => {
+                    let direction: IVec2 = fired.into_iter().map(|(_, direction)| direction).sum();
+                    if direction == IVec2::ZERO {
+                        vec![]
+                    } else {
+                        vec![direction]
                     }
+                }
+            };
 
-                    for in_cell_entity in cell {
-                        let Ok(in_cell) = pullable.get(*in_cell_entity) else {
-                            continue;
-                        };
-
-                        // If 2 OnlyOneInCell would collide, we give up moving.
-                        if let Some(only_1) = in_cell_original.0
-                            && let Some(only_2) = in_cell.0
-                        {
-                            if only_1 == only_2 {
-                                return;
-                            }
-                        }
-                    }
+            // Buffer the moves, so they aren't lost if the selector can't move yet.
+            let buffer_seconds = settings.buffer_seconds;
+            selector.buffer.iter_mut().for_each(|buffered| {
+                buffered.seconds += time_delta_seconds;
+            });
+            selector
+                .buffer
+                .retain(|buffered| buffered.seconds <= buffer_seconds);
+            if !new_moves.is_empty() {
+                // The keys take over from the pointer.
+                selector.targets.clear();
+            }
+            new_moves.into_iter().for_each(|direction| {
+                if selector.buffer.len() < BUFFER_LENGTH {
+                    selector.buffer.push_back(BufferedMove {
+                        direction,
+                        seconds: 0.,
+                    });
                 }
-                info!("Start pull.");
-                pull.into_iter().enumerate().for_each(|(index, entity)| {
-                    info!("{index}");
-                    let Ok(mut on_grid) = on_grid.get_mut(entity) else {
-                        error!("Entity is not in the pullable query.");
-                        return;
-                    };
-                    on_grid.set_translation(&mut grid, entity, translation);
-                });
+            });
+
+            // Only one move a frame. Buffered moves go first, then steps towards the pointer's targets.
+            let can_move_to = |direction: IVec2| {
+                constraints.is_none_or(|constraints| {
+                    constraints.can_move_to(translation.as_ivec2() + direction)
+                })
+            };
+            let (direction, pull, from_pointer) = if let Some(buffered) = selector.buffer.front() {
+                let direction = buffered.direction;
+                if frozen {
+                    // Stay buffered, in case it thaws before the move expires.
+                    return;
+                }
+                selector.buffer.pop_front();
+                if !can_move_to(direction) {
+                    // The selector hasn't moved, so it will never be able to move there. Don't hold up the moves after it.
+                    return;
+                }
+                (direction, selector.pull, false)
+            } else if let Some((direction, pull)) =
+                pointer::next_step(&mut selector.targets, translation)
+            {
+                // Steps follow the same rules as keys, so they can't go a disabled direction either.
+                if !can_move_to(direction)
+                    || constraints.is_some_and(|constraints| constraints.disables(direction))
+                {
+                    selector.targets.clear();
+                    return;
+                }
+                // Locked pull can't be overridden by the pointer either.
+                let pull = if pull_locked { selector.pull } else { pull };
+                (direction, pull, true)
             } else {
-                selector_on_grid.set_translation(&mut grid, selector_entity, translation);
+                return;
+            };
+
+            let mut moving = vec![selector_entity];
+            if pull {
+                let cells = acting_on(&selector.selection, &grid, index);
+                moving.extend(gather(
+                    &cells,
+                    selector.sticky,
+                    &grid,
+                    &pullable,
+                    &pieces,
+                    &on_grid,
+                ));
+            }
+            if selector.push {
+                pushed(&mut moving, direction, &grid, &pullable, &pieces, &on_grid);
+            }
+
+            let moves = moving
+                .into_iter()
+                .map(|entity| (entity, direction))
+                .collect::<Vec<_>>();
+            let moved = try_move(&moves, &mut grid, &mut on_grid, &only_one);
+            if moved && pull {
+                let size = grid.size();
+                move_selection(&mut selector.selection, size, |cell| cell + direction);
             }
+            if !moved && from_pointer {
+                // Blocked, so the rest of the path can't be followed either.
+                selector.targets.clear();
+            }
+        });
+}
+
+/// The indices of the cells the selector acts on.
+/// That is the selection, or the selector's own cell if nothing is selected.
+fn acting_on(selection: &[U8Vec2], grid: &Grid, index: usize) -> Vec<usize> {
+    if selection.is_empty() {
+        return vec![index];
+    }
+    selection
+        .iter()
+        .filter_map(|cell| grid.translation_to_index(*cell))
+        .collect()
+}
+
+/// Everything pulled from the cells.
+/// When sticky, everything connected to them is included too.
+fn gather(
+    cells: &[usize],
+    sticky: bool,
+    grid: &Grid,
+    pullable: &Query<Option<&PieceOf>, With<Pullable>>,
+    pieces: &Query<&Piece>,
+    on_grid: &Query<&mut OnGrid>,
+) -> Vec<Entity> {
+    if sticky {
+        return connected(grid, cells, pullable, pieces, on_grid);
+    }
+    let mut gathered = vec![];
+    cells
+        .iter()
+        .for_each(|index| pulled(grid, *index, pullable, pieces, &mut gathered));
+    gathered
+}
+
+/// Moves the selection along with what was in it.
+/// Any cells that end up outside the grid are deselected.
+fn move_selection(selection: &mut Vec<U8Vec2>, size: U8Vec2, to: impl Fn(IVec2) -> IVec2) {
+    *selection = selection
+        .iter()
+        .map(|cell| to(cell.as_ivec2()))
+        .filter(|cell| cell.cmpge(IVec2::ZERO).all() && cell.cmplt(size.as_ivec2()).all())
+        .map(|cell| cell.as_u8vec2())
+        .collect();
+}
+
+/// Outlines the selected cells, and the box being selected.
+fn draw_selection(
+    mut gizmos: Gizmos,
+    selectors: Query<(&Selector, &OnGrid)>,
+    grids: Query<(&Grid, &GlobalTransform)>,
+) {
+    selectors.iter().for_each(|(selector, on_grid)| {
+        let Ok((grid, transform)) = grids.get(on_grid.entity()) else {
+            return;
+        };
+        let transform = transform.compute_transform();
+
+        let mut outline = |cell: U8Vec2, color: Color| {
+            // On the face of the cell closest to the camera, so the outline isn't hidden inside a body.
+            let position = grid.translation_to_world(&transform, cell)
+                + transform.rotation * Vec3::new(0., 0., -0.51);
+            gizmos.rect(
+                Isometry3d::new(position, transform.rotation),
+                Vec2::splat(0.9),
+                color,
+            );
+        };
+
+        selector
+            .selection
+            .iter()
+            .for_each(|cell| outline(*cell, Color::srgb(1., 0.8, 0.)));
+
+        if let Some(box_start) = selector.box_start {
+            let current = on_grid.translation();
+            let (min, max) = (box_start.min(current), box_start.max(current));
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    outline(U8Vec2::new(x, y), Color::srgb(1., 0.5, 0.));
+                }
+            }
+        }
+    });
+}
+
+/// Adds everything that gets pulled along from the cell to moving.
+/// Pulling any body in a piece pulls the whole piece.
+fn pulled(
+    grid: &Grid,
+    index: usize,
+    pullable: &Query<Option<&PieceOf>, With<Pullable>>,
+    pieces: &Query<&Piece>,
+    moving: &mut Vec<Entity>,
+) {
+    let Some(cell) = grid.cells().get(index) else {
+        error!("Index outside of grid.");
+        return;
+    };
+
+    cell.iter().for_each(|entity| {
+        let Ok(piece_of) = pullable.get(*entity) else {
+            return;
+        };
+
+        let members = piece_of
+            .and_then(|piece_of| pieces.get(piece_of.0).ok())
+            .map_or(std::slice::from_ref(entity), Piece::members);
+        members.iter().for_each(|member| {
+            if !moving.contains(member) {
+                moving.push(*member);
+            }
+        });
+    });
+}
+
+/// Adds everything shoved ahead of moving to it, like pushing boxes in Sokoban.
+/// Each line of cells with pullable entities in it ahead of something moving is pushed, up to the first cell without any.
+fn pushed(
+    moving: &mut Vec<Entity>,
+    direction: IVec2,
+    grid: &Grid,
+    pullable: &Query<Option<&PieceOf>, With<Pullable>>,
+    pieces: &Query<&Piece>,
+    on_grid: &Query<&mut OnGrid>,
+) {
+    // Everything pushed is added to the end of moving, so what is ahead of it gets pushed too.
+    let mut next = 0;
+    while let Some(entity) = moving.get(next).copied() {
+        next += 1;
+        let Ok(entity_on_grid) = on_grid.get(entity) else {
+            continue;
+        };
+        let ahead = entity_on_grid.translation().as_ivec2() + direction;
+        // Anything moving off the grid stops the move anyway.
+        if ahead.cmplt(IVec2::ZERO).any() {
+            continue;
+        }
+        let Some(index) = grid.translation_to_index(ahead.as_u8vec2()) else {
+            continue;
+        };
+        pulled(grid, index, pullable, pieces, moving);
+    }
+}
+
+/// Every pullable entity orthogonally connected to the cells, through other cells with pullable entities in them.
+/// Pieces are always included whole.
+fn connected(
+    grid: &Grid,
+    cells: &[usize],
+    pullable: &Query<Option<&PieceOf>, With<Pullable>>,
+    pieces: &Query<&Piece>,
+    on_grid: &Query<&mut OnGrid>,
+) -> Vec<Entity> {
+    let mut found = vec![];
+    let mut visited = vec![false; grid.cells().len()];
+    let mut to_visit = cells.to_vec();
+
+    while let Some(index) = to_visit.pop() {
+        if visited.get(index).is_none_or(|visited| *visited) {
+            continue;
+        }
+        visited[index] = true;
+
+        let before = found.len();
+        pulled(grid, index, pullable, pieces, &mut found);
+
+        // Visit around everything new, including piece members in other cells.
+        found[before..].iter().for_each(|entity| {
+            let Ok(entity_on_grid) = on_grid.get(*entity) else {
+                return;
+            };
+            let translation = entity_on_grid.translation().as_ivec2();
+            [IVec2::ZERO, IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y]
+                .into_iter()
+                .map(|offset| translation + offset)
+                .filter(|neighbour| neighbour.cmpge(IVec2::ZERO).all())
+                .filter_map(|neighbour| grid.translation_to_index(neighbour.as_u8vec2()))
+                .for_each(|neighbour| to_visit.push(neighbour));
+        });
+    }
+
+    found
+}
+
+/// Moves every entity by its offset, but only if all of them can move.
+/// Nothing can leave the grid, and 2 OnlyOneInCell with the same TypeId can't end up in the same cell, unless the one already there is moving too.
+/// Returns whether they moved.
+fn try_move(
+    moves: &[(Entity, IVec2)],
+    grid: &mut Grid,
+    on_grid: &mut Query<&mut OnGrid>,
+    only_one: &Query<&OnlyOneInCell>,
+) -> bool {
+    let mut destinations = Vec::with_capacity(moves.len());
+    for (entity, offset) in moves {
+        let Ok(entity_on_grid) = on_grid.get(*entity) else {
+            error!("Moving entity is not on grid.");
+            return false;
+        };
+
+        let destination = entity_on_grid.translation().as_ivec2() + *offset;
+        if destination.cmplt(IVec2::ZERO).any() || destination.cmpge(grid.size().as_ivec2()).any() {
+            return false;
+        }
+        destinations.push(destination.as_u8vec2());
+    }
+
+    let moving = |entity: &Entity| moves.iter().any(|(moving, _)| moving == entity);
+
+    for ((entity, _), destination) in moves.iter().zip(&destinations) {
+        let Ok(only) = only_one.get(*entity) else {
+            continue;
+        };
+
+        // Something staying still is already there.
+        let Some(cell) = grid
+            .translation_to_index(*destination)
+            .and_then(|index| grid.cells().get(index))
+        else {
+            error!("Destination is not in grid.");
+            return false;
+        };
+        if cell.iter().any(|in_cell| {
+            !moving(in_cell) && only_one.get(*in_cell).is_ok_and(|other| other == only)
+        }) {
+            return false;
+        }
+
+        // Something else moving is going there too.
+        if moves
+            .iter()
+            .zip(&destinations)
+            .any(|((other, _), other_destination)| {
+                other != entity
+                    && other_destination == destination
+                    && only_one.get(*other).is_ok_and(|other| other == only)
+            })
+        {
+            return false;
+        }
+    }
+
+    moves
+        .iter()
+        .zip(destinations)
+        .for_each(|((entity, _), destination)| {
+            let Ok(mut entity_on_grid) = on_grid.get_mut(*entity) else {
+                error!("Moving entity is not on grid.");
+                return;
+            };
+            entity_on_grid.set_translation(grid, *entity, destination);
         });
+    true
 }
@@ -0,0 +1,111 @@
+use super::{DIRECTIONS, Selector};
+use crate::{actions::Action, battle::state::BattleState};
+use bevy::{math::U8Vec2, prelude::*};
+use std::str::FromStr;
+
+pub fn plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        (lock_pull, thaw)
+            .run_if(in_state(BattleState::Dodging).or(in_state(BattleState::Resolving))),
+    );
+}
+
+/// Limits what the player can do with a selector, so encounters can script the selector.
+/// Put on a selector. Can be authored with a battle definition's selector section.
+#[derive(Component, Clone, Default, Debug)]
+pub struct SelectorConstraints {
+    /// Forces pull on or off, and stops the player toggling it.
+    /// Selector::pull_locked isn't changed, so it still applies once this is None again.
+    pub pull: Option<bool>,
+    /// The selector can't move until this reaches 0.
+    pub frozen_seconds: f32,
+    /// The selector can't move outside of this.
+    pub region: Option<Region>,
+    /// The movement actions that do nothing. The pointer can't step in their directions either.
+    pub disabled_directions: Vec<Action>,
+}
+
+impl SelectorConstraints {
+    /// Can the selector move to the translation right now.
+    /// Doesn't check the direction, as the move could be diagonal.
+    pub fn can_move_to(&self, to: IVec2) -> bool {
+        !self.frozen() && self.in_region(to)
+    }
+
+    /// Is the direction one of the disabled movement actions, so the selector can't step that way.
+    pub fn disables(&self, direction: IVec2) -> bool {
+        DIRECTIONS.iter().any(|(action, action_direction)| {
+            *action_direction == direction && self.disabled_directions.contains(action)
+        })
+    }
+
+    pub fn frozen(&self) -> bool {
+        self.frozen_seconds > 0.
+    }
+
+    pub fn in_region(&self, translation: IVec2) -> bool {
+        self.region
+            .is_none_or(|region| region.contains(translation))
+    }
+}
+
+/// A rectangle of cells on a grid.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Region {
+    pub min: U8Vec2,
+    /// Inclusive.
+    pub max: U8Vec2,
+}
+
+impl Region {
+    pub fn contains(self, translation: IVec2) -> bool {
+        translation.cmpge(self.min.as_ivec2()).all() && translation.cmple(self.max.as_ivec2()).all()
+    }
+}
+
+/// Parses `min_x,min_y,max_x,max_y`. The corners can be in any order.
+impl FromStr for Region {
+    type Err = ();
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let numbers = text
+            .split(',')
+            .map(|number| number.trim().parse::<u8>().map_err(|_| ()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let [min_x, min_y, max_x, max_y] = numbers[..] else {
+            return Err(());
+        };
+        let (a, b) = (U8Vec2::new(min_x, min_y), U8Vec2::new(max_x, max_y));
+        Ok(Self {
+            min: a.min(b),
+            max: a.max(b),
+        })
+    }
+}
+
+/// Keeps pull where the constraints force it.
+/// The selector's pull_locked is left alone, as movement checks the constraints for that itself.
+fn lock_pull(mut selectors: Query<(&mut Selector, &SelectorConstraints)>) {
+    selectors
+        .iter_mut()
+        .for_each(|(mut selector, constraints)| {
+            if let Some(pull) = constraints.pull
+                && selector.pull != pull
+            {
+                selector.pull = pull;
+            }
+        });
+}
+
+/// Counts down how long selectors are frozen for.
+fn thaw(mut constraints: Query<&mut SelectorConstraints>, time: Res<Time>) {
+    let time_delta_seconds = time.delta_secs();
+    constraints.iter_mut().for_each(|mut constraints| {
+        if constraints.frozen() {
+            // Counting down isn't a change anything needs to react to.
+            let constraints = constraints.bypass_change_detection();
+            constraints.frozen_seconds = (constraints.frozen_seconds - time_delta_seconds).max(0.);
+        }
+    });
+}
@@ -0,0 +1,134 @@
+use super::Selector;
+use crate::{
+    battle::{
+        grid::{Grid, on_grid::OnGrid},
+        state::BattleState,
+    },
+    replay::{Recording, Replay},
+};
+use bevy::{math::U8Vec2, prelude::*, window::PrimaryWindow};
+use std::collections::VecDeque;
+
+pub fn plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        pointer
+            .run_if(in_state(BattleState::Dodging).or(in_state(BattleState::Resolving)))
+            // Replays only have actions, so the pointer would make them play out differently.
+            .run_if(not(resource_exists::<Replay>).and(not(resource_exists::<Recording>))),
+    );
+}
+
+/// A cell for the selector to step to.
+pub struct PointerTarget {
+    cell: U8Vec2,
+    /// Pull along the way there.
+    pull: bool,
+}
+
+/// The mouse button or touch that is held down.
+pub struct PointerPress {
+    /// Where it was pressed.
+    cell: U8Vec2,
+    /// The last cell it was over.
+    last: U8Vec2,
+    /// Has it left the cell it was pressed in.
+    dragging: bool,
+}
+
+/// Clicking or tapping a cell moves the selector there.
+/// Dragging moves the selector to where the drag started, then pulls along the path of the drag.
+/// The selector steps a cell at a time, with the same rules as moving with keys.
+fn pointer(
+    mut selectors: Query<(&mut Selector, &OnGrid)>,
+    grids: Query<(&Grid, &GlobalTransform)>,
+    camera: Single<(&Camera, &GlobalTransform), With<Camera3d>>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    touches: Res<Touches>,
+) {
+    let just_pressed = mouse.just_pressed(MouseButton::Left) || touches.any_just_pressed();
+    let pressed = mouse.pressed(MouseButton::Left) || touches.iter().next().is_some();
+    let just_released = mouse.just_released(MouseButton::Left) || touches.any_just_released();
+    let position = touches
+        .first_pressed_position()
+        .or_else(|| window.cursor_position());
+    let (camera, camera_transform) = *camera;
+
+    selectors.iter_mut().for_each(|(mut selector, on_grid)| {
+        let selector = &mut *selector;
+        let Ok((grid, grid_transform)) = grids.get(on_grid.entity()) else {
+            return;
+        };
+        let grid_transform = grid_transform.compute_transform();
+
+        // The cell under the pointer, found by casting a ray from the camera onto the grid's plane.
+        let hovered = position
+            .and_then(|position| camera.viewport_to_world(camera_transform, position).ok())
+            .and_then(|ray| {
+                let normal = grid_transform.rotation * Vec3::Z;
+                let distance =
+                    ray.intersect_plane(grid_transform.translation, InfinitePlane3d::new(normal))?;
+                grid.world_to_translation(&grid_transform, ray.get_point(distance))
+            });
+
+        if just_pressed {
+            selector.pointer_press = hovered.map(|cell| PointerPress {
+                cell,
+                last: cell,
+                dragging: false,
+            });
+        } else if pressed
+            && let Some(press) = &mut selector.pointer_press
+            && let Some(cell) = hovered
+            && cell != press.last
+        {
+            if !press.dragging {
+                press.dragging = true;
+                selector.targets.clear();
+                selector.targets.push_back(PointerTarget {
+                    cell: press.cell,
+                    pull: false,
+                });
+            }
+            press.last = cell;
+            selector
+                .targets
+                .push_back(PointerTarget { cell, pull: true });
+        }
+
+        if just_released
+            && let Some(press) = selector.pointer_press.take()
+            && !press.dragging
+        {
+            selector.targets.clear();
+            selector.targets.push_back(PointerTarget {
+                cell: press.cell,
+                pull: false,
+            });
+        }
+    });
+}
+
+/// The direction of the next step towards the first target that hasn't been reached, and whether to pull.
+/// Reached targets are removed. Steps horizontally first.
+pub fn next_step(
+    targets: &mut VecDeque<PointerTarget>,
+    translation: U8Vec2,
+) -> Option<(IVec2, bool)> {
+    while let Some(target) = targets.front() {
+        let offset = target.cell.as_ivec2() - translation.as_ivec2();
+        if offset == IVec2::ZERO {
+            targets.pop_front();
+            continue;
+        }
+
+        let direction = if offset.x != 0 {
+            IVec2::new(offset.x.signum(), 0)
+        } else {
+            IVec2::new(0, offset.y.signum())
+        };
+        return Some((direction, target.pull));
+    }
+    None
+}
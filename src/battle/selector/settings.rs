@@ -0,0 +1,117 @@
+use crate::config;
+use bevy::prelude::*;
+use std::str::FromStr;
+
+pub fn plugin(app: &mut App) {
+    app.insert_resource(MovementSettings::load())
+        .add_systems(Update, save.run_if(config::changed::<MovementSettings>));
+}
+
+/// The file in the config directory the settings are kept in.
+const FILE_NAME: &str = "movement.txt";
+
+/// How the selector repeats moves while a direction is held, like DAS and ARR in block puzzle games.
+/// Loaded from the config directory on startup, so players can tune it, and saved there whenever it changes.
+#[derive(Resource)]
+pub struct MovementSettings {
+    /// How long a direction has to be held before it starts repeating.
+    pub initial_delay: f32,
+    /// How long between each repeat. At 0, it moves every frame.
+    pub repeat_interval: f32,
+    /// How long a move waits in the buffer for the selector to be able to move, before it is dropped.
+    pub buffer_seconds: f32,
+    pub simultaneous: Simultaneous,
+}
+
+impl Default for MovementSettings {
+    fn default() -> Self {
+        Self {
+            initial_delay: 0.15,
+            repeat_interval: 0.15,
+            buffer_seconds: 0.1,
+            simultaneous: Simultaneous::Queue,
+        }
+    }
+}
+
+/// What happens when more than one direction is pressed in the same frame.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Simultaneous {
+    /// Each direction is a separate move, one after another.
+    Queue,
+    /// None of them move.
+    Cancel,
+    /// Only the direction pressed most recently moves, even while others are still held.
+    LastPressedWins,
+    /// They are combined into one move, so up and left moves diagonally. Opposite directions cancel out.
+    Diagonal,
+}
+
+impl Simultaneous {
+    /// The name it is saved as.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Queue => "queue",
+            Self::Cancel => "cancel",
+            Self::LastPressedWins => "last_pressed_wins",
+            Self::Diagonal => "diagonal",
+        }
+    }
+}
+
+impl FromStr for Simultaneous {
+    type Err = ();
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "queue" => Ok(Self::Queue),
+            "cancel" => Ok(Self::Cancel),
+            "last_pressed_wins" => Ok(Self::LastPressedWins),
+            "diagonal" => Ok(Self::Diagonal),
+            _ => Err(()),
+        }
+    }
+}
+
+impl MovementSettings {
+    /// Loads the saved settings. Anything that isn't saved, or can't be read, uses the default.
+    /// If nothing has been saved yet, the defaults are saved, so there is a file to edit.
+    fn load() -> Self {
+        let mut settings = Self::default();
+        if config::load(FILE_NAME, |key, value| settings.set(key, value)) {
+            settings.save();
+        }
+        settings
+    }
+
+    fn save(&self) {
+        config::save(FILE_NAME, self.lines());
+    }
+
+    /// Sets a setting from its `key = value` line.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let invalid = || format!("Invalid value {value:?}.");
+        match key {
+            "initial_delay" => self.initial_delay = value.parse().map_err(|_| invalid())?,
+            "repeat_interval" => self.repeat_interval = value.parse().map_err(|_| invalid())?,
+            "buffer_seconds" => self.buffer_seconds = value.parse().map_err(|_| invalid())?,
+            "simultaneous" => self.simultaneous = value.parse().map_err(|_| invalid())?,
+            _ => return Err(format!("Unknown setting {key:?}.")),
+        }
+        Ok(())
+    }
+
+    /// Every setting as a `key = value` line.
+    pub fn lines(&self) -> [(&'static str, String); 4] {
+        [
+            ("initial_delay", self.initial_delay.to_string()),
+            ("repeat_interval", self.repeat_interval.to_string()),
+            ("buffer_seconds", self.buffer_seconds.to_string()),
+            ("simultaneous", self.simultaneous.name().to_owned()),
+        ]
+    }
+}
+
+fn save(settings: Res<MovementSettings>) {
+    settings.save();
+}
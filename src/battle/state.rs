@@ -0,0 +1,122 @@
+use super::{FromBattle, PlayerGrid, health::DefeatEvent, wave::FinalWallCollidedEvent};
+use crate::actions::{Action, Actions};
+use bevy::prelude::*;
+
+pub fn plugin(app: &mut App) {
+    app.init_state::<BattleState>()
+        .add_systems(Startup, start)
+        .add_systems(OnEnter(BattleState::Resolving), start_resolving)
+        .add_systems(
+            Update,
+            (
+                dodge.run_if(in_state(BattleState::Intro)),
+                resolve.run_if(in_state(BattleState::Dodging)),
+                win.run_if(in_state(BattleState::Resolving)),
+                lose.run_if(in_state(BattleState::Dodging).or(in_state(BattleState::Resolving))),
+                restart.run_if(
+                    in_state(BattleState::Victory)
+                        .or(in_state(BattleState::Defeat))
+                        .or(in_state(BattleState::Exited)),
+                ),
+                exit.run_if(not(in_state(BattleState::Exited))),
+            )
+                // Later transitions win, so losing beats winning on the same frame.
+                .chain()
+                .in_set(BattleTransitions),
+        );
+}
+
+/// The systems that change the BattleState during Update.
+/// Anything that has to win over them should run after this.
+#[derive(SystemSet, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct BattleTransitions;
+
+/// Where we are in a battle.
+/// Entering Intro or Exited cleans up the previous battle, and the current battle is spawned during Intro once it has loaded.
+#[derive(States, Default, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum BattleState {
+    /// The battle has just been spawned.
+    Intro,
+    /// Opponent grids are approaching and the player can rearrange themselves.
+    Dodging,
+    /// The final wall has collided, waiting for the last of the damage before deciding who won.
+    Resolving,
+    Victory,
+    Defeat,
+    /// Not in a battle.
+    #[default]
+    Exited,
+}
+
+/// How long resolving lasts.
+const RESOLVING_SECONDS: f32 = 1.;
+
+/// Counts down until the battle is resolved.
+/// Singleton.
+#[derive(Component)]
+#[require(FromBattle)]
+struct ResolvingTimer(Timer);
+
+/// Temp. There is nothing to choose a battle yet, so just start one.
+fn start(mut next_state: ResMut<NextState<BattleState>>) {
+    next_state.set(BattleState::Intro);
+}
+
+/// There is no intro yet, so go straight into dodging once the battle has spawned.
+fn dodge(player: Query<(), With<PlayerGrid>>, mut next_state: ResMut<NextState<BattleState>>) {
+    if !player.is_empty() {
+        next_state.set(BattleState::Dodging);
+    }
+}
+
+/// Once the final wall has collided, there is nothing left to dodge.
+fn resolve(
+    mut final_wall_collided_event: EventReader<FinalWallCollidedEvent>,
+    mut next_state: ResMut<NextState<BattleState>>,
+) {
+    if final_wall_collided_event.read().next().is_some() {
+        next_state.set(BattleState::Resolving);
+    }
+}
+
+fn start_resolving(mut commands: Commands) {
+    commands.spawn(ResolvingTimer(Timer::from_seconds(
+        RESOLVING_SECONDS,
+        TimerMode::Once,
+    )));
+}
+
+/// If the player survived resolving, they won.
+fn win(
+    timer: Option<Single<&mut ResolvingTimer>>,
+    mut next_state: ResMut<NextState<BattleState>>,
+    time: Res<Time>,
+) {
+    let Some(mut timer) = timer else {
+        return;
+    };
+    if timer.0.tick(time.delta()).just_finished() {
+        next_state.set(BattleState::Victory);
+    }
+}
+
+fn lose(
+    mut defeat_event: EventReader<DefeatEvent>,
+    mut next_state: ResMut<NextState<BattleState>>,
+) {
+    if defeat_event.read().next().is_some() {
+        next_state.set(BattleState::Defeat);
+    }
+}
+
+fn restart(actions: Actions, mut next_state: ResMut<NextState<BattleState>>) {
+    if actions.just_pressed(&Action::Restart) {
+        next_state.set(BattleState::Intro);
+    }
+}
+
+fn exit(actions: Actions, mut next_state: ResMut<NextState<BattleState>>) {
+    if actions.just_pressed(&Action::Exit) {
+        next_state.set(BattleState::Exited);
+    }
+}
@@ -0,0 +1,41 @@
+use super::{CubeMesh, FromBattle, fit::Solid};
+use bevy::prelude::*;
+
+pub fn plugin(app: &mut App) {
+    app.add_systems(PreStartup, material);
+}
+
+/// A solid block in an opponent grid.
+/// Any cell without one of these is a hole.
+#[derive(Component, Default)]
+#[require(Solid, FromBattle)]
+pub struct Wall;
+
+impl Wall {
+    pub fn on_grid() -> impl Fn(&mut Commands) -> Entity {
+        |commands| {
+            let mut entity = commands.spawn(Wall);
+            entity.queue(|mut entity: EntityWorldMut| {
+                let cube_mesh = entity.world().resource::<CubeMesh>().0.clone();
+                let wall_material = entity.world().resource::<Material>().0.clone();
+                entity.insert((Mesh3d(cube_mesh), MeshMaterial3d(wall_material)));
+            });
+            entity.id()
+        }
+    }
+}
+
+#[derive(Resource)]
+struct Material(Handle<StandardMaterial>);
+
+fn material(world: &mut World) {
+    let asset_server = world.resource::<AssetServer>();
+    let material = Material(asset_server.add(StandardMaterial {
+        base_color_texture: None,
+        base_color: Color::srgb(0.5, 0.5, 0.5),
+        unlit: true,
+        alpha_mode: AlphaMode::Opaque,
+        ..default()
+    }));
+    world.insert_resource(material);
+}
@@ -0,0 +1,152 @@
+use super::{
+    FromBattle,
+    definition::{Trigger, WallDefinition, WaveDefinition, wall_spawners},
+    opponent::{CollisionEvent, OpponentGrid},
+    state::BattleState,
+};
+use bevy::prelude::*;
+
+pub fn plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        (
+            spawn_walls.run_if(in_state(BattleState::Dodging)),
+            final_wall_collided,
+        ),
+    )
+    .add_event::<WaveStartedEvent>()
+    .add_event::<FinalWallCollidedEvent>();
+}
+
+/// How far away from the player opponent grids spawn.
+const SPAWN_DISTANCE: f32 = 20.;
+
+/// Spawns the opponent grids of a battle one after another.
+/// Singleton.
+#[derive(Component)]
+#[require(FromBattle)]
+pub struct WaveSequencer {
+    waves: Vec<WaveDefinition>,
+    /// The wave and the index in that wave of the wall that spawns next.
+    next_index: (usize, usize),
+    seconds_since_last_spawn: f32,
+    /// The last wall to spawn.
+    previous: Option<Entity>,
+    /// Only set once the final wall has spawned.
+    final_wall: Option<Entity>,
+}
+
+impl WaveSequencer {
+    pub fn new(waves: Vec<WaveDefinition>) -> Self {
+        let mut sequencer = Self {
+            waves,
+            next_index: (0, 0),
+            seconds_since_last_spawn: 0.,
+            previous: None,
+            final_wall: None,
+        };
+        sequencer.skip_empty_waves();
+        sequencer
+    }
+
+    /// The wall that spawns next, or None if every wall has spawned.
+    pub fn next(&self) -> Option<&WallDefinition> {
+        self.waves
+            .get(self.next_index.0)?
+            .walls
+            .get(self.next_index.1)
+    }
+
+    fn skip_empty_waves(&mut self) {
+        while self
+            .waves
+            .get(self.next_index.0)
+            .is_some_and(|wave| self.next_index.1 >= wave.walls.len())
+        {
+            self.next_index = (self.next_index.0 + 1, 0);
+        }
+    }
+}
+
+/// When the first wall of a wave spawns.
+#[derive(Event, Debug)]
+pub struct WaveStartedEvent;
+
+/// When the final wall of the final wave has collided with the player grid.
+#[derive(Event, Debug)]
+pub struct FinalWallCollidedEvent;
+
+fn spawn_walls(
+    mut commands: Commands,
+    sequencer: Option<Single<&mut WaveSequencer>>,
+    grids: Query<&Transform, With<OpponentGrid>>,
+    mut wave_started_event: EventWriter<WaveStartedEvent>,
+    time: Res<Time>,
+) {
+    let Some(mut sequencer) = sequencer else {
+        return;
+    };
+    sequencer.seconds_since_last_spawn += time.delta_secs();
+
+    let Some(wall) = sequencer.next() else {
+        return;
+    };
+
+    let ready = match (wall.trigger, sequencer.previous) {
+        (Trigger::Delay(delay), _) => sequencer.seconds_since_last_spawn >= delay,
+        (Trigger::Spacing(spacing), Some(previous)) => grids
+            .get(previous)
+            // If the previous wall is gone, it has definitely moved far enough.
+            .map_or(true, |transform| {
+                SPAWN_DISTANCE - transform.translation.z >= spacing
+            }),
+        (Trigger::Spacing(_), None) => true,
+    };
+    if !ready {
+        return;
+    }
+
+    let spawners = wall_spawners();
+    let opponent = match wall.layout.spawn(&mut commands, &spawners) {
+        Ok(opponent) => opponent,
+        Err(error) => {
+            error!("Could not spawn an opponent grid: {error}");
+            return;
+        }
+    };
+    commands.entity(opponent).insert((
+        Transform::from_xyz(0., 0., SPAWN_DISTANCE),
+        OpponentGrid::new(wall.speed),
+    ));
+
+    if sequencer.next_index.1 == 0 {
+        info!("Wave {} started.", sequencer.next_index.0);
+        wave_started_event.send(WaveStartedEvent);
+    }
+
+    sequencer.next_index.1 += 1;
+    sequencer.skip_empty_waves();
+    sequencer.seconds_since_last_spawn = 0.;
+    sequencer.previous = Some(opponent);
+    if sequencer.next().is_none() {
+        sequencer.final_wall = Some(opponent);
+    }
+}
+
+fn final_wall_collided(
+    mut collision_event: EventReader<CollisionEvent>,
+    mut final_wall_collided_event: EventWriter<FinalWallCollidedEvent>,
+    sequencer: Option<Single<&WaveSequencer>>,
+) {
+    let Some(final_wall) = sequencer.and_then(|sequencer| sequencer.final_wall) else {
+        collision_event.clear();
+        return;
+    };
+
+    if collision_event
+        .read()
+        .any(|collision_event| collision_event.opponent == final_wall)
+    {
+        final_wall_collided_event.send(FinalWallCollidedEvent);
+    }
+}
@@ -0,0 +1,67 @@
+use bevy::prelude::*;
+use std::{fmt::Display, path::PathBuf};
+
+/// Where a config file is kept, in the user's config directory.
+/// None if there is no config directory.
+pub fn path(file_name: &str) -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join("silly_game").join(file_name))
+}
+
+/// Reads each `key = value` line of a config file, skipping blank lines and `#` comments.
+/// `set` returns why a line couldn't be used, which is logged with where it is, and the rest of the file is still read.
+/// Returns true if the file doesn't exist yet, so the defaults can be saved for the player to find.
+pub fn load(file_name: &str, mut set: impl FnMut(&str, &str) -> Result<(), String>) -> bool {
+    let Some(path) = path(file_name) else {
+        return false;
+    };
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return true,
+        Err(error) => {
+            error!("Could not read {}: {error}", path.display());
+            return false;
+        }
+    };
+
+    for (line, text) in text.lines().enumerate() {
+        let line = line + 1;
+        let text = text.trim();
+        if text.is_empty() || text.starts_with('#') {
+            continue;
+        }
+
+        let Some((key, value)) = text.split_once('=') else {
+            error!("{}:{line}: Expected `key = value`.", path.display());
+            continue;
+        };
+        if let Err(message) = set(key.trim(), value.trim()) {
+            error!("{}:{line}: {message}", path.display());
+        }
+    }
+    false
+}
+
+/// Writes each `key = value` line to a config file, replacing what was there.
+pub fn save(file_name: &str, lines: impl IntoIterator<Item = (impl Display, impl Display)>) {
+    let Some(path) = path(file_name) else {
+        error!("Could not save {file_name}, as there is no config directory.");
+        return;
+    };
+
+    let text = lines
+        .into_iter()
+        .map(|(key, value)| format!("{key} = {value}\n"))
+        .collect::<String>();
+    let result = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::write(&path, text));
+    if let Err(error) = result {
+        error!("Could not save {}: {error}", path.display());
+    }
+}
+
+/// Has a config resource changed since it was loaded, so it needs saving.
+pub fn changed<R: Resource>(resource: Option<Res<R>>) -> bool {
+    resource.is_some_and(|resource| resource.is_changed() && !resource.is_added())
+}
@@ -4,11 +4,18 @@ use bevy::prelude::*;
 
 mod actions;
 mod battle;
+mod config;
 mod create;
 mod events;
+mod replay;
 
 fn main() {
     App::new()
-        .add_plugins((DefaultPlugins, actions::plugin, battle::plugin))
+        .add_plugins((
+            DefaultPlugins,
+            actions::plugin,
+            battle::plugin,
+            replay::plugin,
+        ))
         .run();
 }
@@ -0,0 +1,249 @@
+use crate::{
+    actions::{Action, bindings::Bindings, input_map},
+    battle::{PlayerGrid, selector::settings::MovementSettings},
+};
+use bevy::{prelude::*, reflect::Enum, time::TimeUpdateStrategy};
+use leafwing_input_manager::{plugin::InputManagerSystem, prelude::*};
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    time::Duration,
+};
+
+/// Records or replays the actions pressed each frame, and how long each frame took.
+/// `--record path` records to the file, and `--replay path` replays it.
+/// Both start from the first frame the battle exists, so loading times don't matter.
+/// The movement settings are recorded too, and used while replaying instead of the player's own.
+/// Only actions are recorded, so the pointer can't move the selector while recording or replaying.
+/// Bindings can't be changed while replaying either.
+pub fn plugin(app: &mut App) {
+    let mut record = None;
+    let mut replay = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--record" => record = args.next(),
+            "--replay" => replay = args.next(),
+            _ => {}
+        }
+    }
+
+    if let Some(path) = replay {
+        if record.is_some() {
+            warn!("Can't record while replaying, so only replaying.");
+        }
+        match Replay::load(&path) {
+            Ok(replay) => {
+                info!("Replaying {} frames from {path}.", replay.frames.len());
+                app.insert_resource(replay);
+            }
+            Err(error) => error!("Could not load the replay from {path}: {error}"),
+        }
+    } else if let Some(path) = record {
+        match File::create(&path) {
+            Ok(file) => {
+                info!("Recording to {path}.");
+                app.insert_resource(Recording {
+                    file: BufWriter::new(file),
+                    started: false,
+                });
+            }
+            Err(error) => error!("Could not create the recording {path}: {error}"),
+        }
+    }
+
+    app.add_systems(
+        PreUpdate,
+        (
+            ignore_input
+                .before(InputManagerSystem::Update)
+                .run_if(resource_exists::<Replay>),
+            replay_actions
+                .in_set(InputManagerSystem::ManualControl)
+                .run_if(resource_exists::<Replay>),
+            record
+                .after(InputManagerSystem::ManualControl)
+                .run_if(resource_exists::<Recording>),
+        ),
+    )
+    .add_systems(Last, replay_time.run_if(resource_exists::<Replay>));
+}
+
+/// A frame of a recording.
+struct Frame {
+    delta: Duration,
+    pressed: Vec<Action>,
+}
+
+/// Starts with a line of `settings key=value key=value` for the movement settings.
+/// Then each frame is written as a line of `delta_nanoseconds Action,Action`.
+#[derive(Resource)]
+pub struct Recording {
+    file: BufWriter<File>,
+    /// Has the battle existed yet.
+    started: bool,
+}
+
+#[derive(Resource)]
+pub struct Replay {
+    /// The recorded settings until the replay starts, then the player's own until it finishes.
+    settings: MovementSettings,
+    frames: Vec<Frame>,
+    /// The next frame to replay.
+    next: usize,
+    /// Has the battle existed yet.
+    started: bool,
+}
+
+impl Replay {
+    fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+        let mut lines = text
+            .lines()
+            .map(str::trim)
+            .enumerate()
+            .filter(|(_, text)| !text.is_empty() && !text.starts_with('#'));
+
+        let mut settings = MovementSettings::default();
+        let (line, header) = lines.next().unwrap_or_default();
+        let line = line + 1;
+        let mut header = header.split_whitespace();
+        if header.next() != Some("settings") {
+            return Err(format!("Line {line}: Expected the settings."));
+        }
+        for setting in header {
+            let (key, value) = setting
+                .split_once('=')
+                .ok_or_else(|| format!("Line {line}: Expected `key=value`."))?;
+            settings
+                .set(key, value)
+                .map_err(|message| format!("Line {line}: {message}"))?;
+        }
+
+        let frames = lines
+            .map(|(line, text)| {
+                let line = line + 1;
+                let (delta, pressed) = text.split_once(' ').unwrap_or((text, ""));
+                let delta = delta
+                    .parse()
+                    .map(Duration::from_nanos)
+                    .map_err(|_| format!("Line {line}: Invalid delta \"{delta}\"."))?;
+                let pressed = pressed
+                    .split(',')
+                    .filter(|name| !name.is_empty())
+                    .map(|name| {
+                        Action::ALL
+                            .into_iter()
+                            .find(|action| action.variant_name() == name)
+                            .ok_or_else(|| format!("Line {line}: Unknown action \"{name}\"."))
+                    })
+                    .collect::<Result<_, _>>()?;
+                Ok(Frame { delta, pressed })
+            })
+            .collect::<Result<_, String>>()?;
+
+        Ok(Self {
+            settings,
+            frames,
+            next: 0,
+            started: false,
+        })
+    }
+}
+
+fn record(
+    mut recording: ResMut<Recording>,
+    action_state: Res<ActionState<Action>>,
+    player: Query<(), With<PlayerGrid>>,
+    settings: Res<MovementSettings>,
+    time: Res<Time>,
+) {
+    if !recording.started {
+        if player.is_empty() {
+            return;
+        }
+        recording.started = true;
+
+        let settings = settings
+            .lines()
+            .map(|(key, value)| format!(" {key}={value}"))
+            .concat();
+        if let Err(error) = writeln!(recording.file, "settings{settings}") {
+            error_once!("Could not write to the recording: {error}");
+        }
+    }
+
+    let pressed = Action::ALL
+        .iter()
+        .filter(|action| action_state.pressed(action))
+        .map(|action| action.variant_name())
+        .collect::<Vec<_>>()
+        .join(",");
+    // Flushed every frame, so crashes are still recorded.
+    let result = writeln!(recording.file, "{} {pressed}", time.delta().as_nanos())
+        .and_then(|_| recording.file.flush());
+    if let Err(error) = result {
+        error_once!("Could not write to the recording: {error}");
+    }
+}
+
+/// Stops real input from changing the actions while replaying.
+/// Actions that aren't in the input map are left as they are.
+fn ignore_input(mut input_map: ResMut<InputMap<Action>>) {
+    *input_map = InputMap::default();
+}
+
+/// Sets how long the next frame takes, so time passes the same as when it was recorded.
+fn replay_time(
+    mut commands: Commands,
+    mut replay: ResMut<Replay>,
+    mut settings: ResMut<MovementSettings>,
+    player: Query<(), With<PlayerGrid>>,
+) {
+    if !replay.started {
+        if player.is_empty() {
+            return;
+        }
+        replay.started = true;
+        // Bypassed, so the recorded settings aren't saved over the player's.
+        std::mem::swap(settings.bypass_change_detection(), &mut replay.settings);
+    }
+
+    if let Some(frame) = replay.frames.get(replay.next) {
+        commands.insert_resource(TimeUpdateStrategy::ManualDuration(frame.delta));
+    }
+}
+
+fn replay_actions(
+    mut commands: Commands,
+    mut replay: ResMut<Replay>,
+    mut action_state: ResMut<ActionState<Action>>,
+    mut settings: ResMut<MovementSettings>,
+    bindings: Res<Bindings>,
+) {
+    if !replay.started {
+        return;
+    }
+
+    let Some(frame) = replay.frames.get(replay.next) else {
+        info!("The replay has finished.");
+        Action::ALL
+            .iter()
+            .for_each(|action| action_state.release(action));
+        commands.insert_resource(input_map(&bindings));
+        commands.insert_resource(TimeUpdateStrategy::Automatic);
+        // Put the player's own settings back.
+        std::mem::swap(settings.bypass_change_detection(), &mut replay.settings);
+        commands.remove_resource::<Replay>();
+        return;
+    };
+
+    Action::ALL.iter().for_each(|action| {
+        if frame.pressed.contains(action) {
+            action_state.press(action);
+        } else {
+            action_state.release(action);
+        }
+    });
+    replay.next += 1;
+}